@@ -0,0 +1,77 @@
+use cosmwasm_std::Binary;
+use k256::ecdsa::signature::Signer;
+use k256::ecdsa::{Signature, SigningKey, VerifyingKey};
+use rand::rngs::OsRng;
+use rsa::pkcs8::{FromPublicKey, ToPublicKey};
+use rsa::{PaddingScheme, PublicKey, RsaPrivateKey, RsaPublicKey};
+use thiserror::Error;
+use crate::helper::{permit_message, ADDRESS_ENVELOPE_VERSION};
+use crate::state::{AddressEnvelope, EncryptionAlgorithm};
+
+#[derive(Error, Debug)]
+pub enum CryptoError {
+    #[error("{0}")]
+    Rsa(#[from] rsa::errors::Error),
+
+    #[error("{0}")]
+    Pkcs8(#[from] rsa::pkcs8::Error),
+
+    #[error("UnsupportedAlgorithm")]
+    UnsupportedAlgorithm {},
+
+    #[error("InvalidAddress")]
+    InvalidAddress {}
+}
+
+// Returns the private key together with the PEM encoded public key a shipper registers or bids with
+pub fn generate_rsa_key(bits: usize) -> Result<(RsaPrivateKey, String), CryptoError> {
+    let private_key = RsaPrivateKey::new(&mut OsRng, bits)?;
+    let pub_key = RsaPublicKey::from(&private_key).to_public_key_pem()?;
+    Ok((private_key, pub_key))
+}
+
+// Raw ciphertext for the legacy UploadAddress message
+pub fn encrypt_address_legacy(pub_key: &str, address: &str) -> Result<Vec<u8>, CryptoError> {
+    let key = RsaPublicKey::from_public_key_pem(pub_key)?;
+    Ok(key.encrypt(&mut OsRng, PaddingScheme::new_pkcs1v15_encrypt(), address.as_bytes())?)
+}
+
+pub fn encrypt_address(pub_key: &str, algorithm: EncryptionAlgorithm, address: &str) -> Result<AddressEnvelope, CryptoError> {
+    let padding = match algorithm {
+        EncryptionAlgorithm::RsaPkcs1v15 => PaddingScheme::new_pkcs1v15_encrypt(),
+        EncryptionAlgorithm::RsaOaep => PaddingScheme::new_oaep::<sha2::Sha256>(),
+        EncryptionAlgorithm::X25519Ecies => return Err(CryptoError::UnsupportedAlgorithm {})
+    };
+    let key = RsaPublicKey::from_public_key_pem(pub_key)?;
+    let ciphertext = key.encrypt(&mut OsRng, padding, address.as_bytes())?;
+    Ok(AddressEnvelope {
+        algorithm,
+        version: ADDRESS_ENVELOPE_VERSION,
+        nonce: None,
+        ciphertext: Binary::from(ciphertext)
+    })
+}
+
+// Accepts envelopes of any version, including the version 0 wrappers of legacy uploads
+pub fn decrypt_address(private_key: &RsaPrivateKey, envelope: &AddressEnvelope) -> Result<String, CryptoError> {
+    let padding = match envelope.algorithm {
+        EncryptionAlgorithm::RsaPkcs1v15 => PaddingScheme::new_pkcs1v15_encrypt(),
+        EncryptionAlgorithm::RsaOaep => PaddingScheme::new_oaep::<sha2::Sha256>(),
+        EncryptionAlgorithm::X25519Ecies => return Err(CryptoError::UnsupportedAlgorithm {})
+    };
+    let address = private_key.decrypt(padding, envelope.ciphertext.as_slice())?;
+    String::from_utf8(address).map_err(|_| CryptoError::InvalidAddress {})
+}
+
+// Returns the signing key together with the compressed public key to register with SetPermitKey
+pub fn generate_permit_key() -> (SigningKey, Binary) {
+    let signing_key = SigningKey::random(OsRng);
+    let pub_key = VerifyingKey::from(&signing_key).to_bytes();
+    (signing_key, Binary::from(&pub_key[..]))
+}
+
+// Signature to pass as Viewer.signature when querying the given contract as address
+pub fn sign_permit(signing_key: &SigningKey, contract: &str, address: &str) -> Binary {
+    let signature: Signature = signing_key.sign(&permit_message(contract, address));
+    Binary::from(signature.as_ref())
+}
@@ -0,0 +1,2 @@
+// Off-chain helpers shared by front-ends and tests, enabled with the `client` feature.
+pub mod crypto;
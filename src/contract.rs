@@ -1,25 +1,34 @@
-use std::iter::Map;
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Coin, Uint128, from_binary, AllBalanceResponse, Addr, CosmosMsg, BankMsg, Pair};
-use cosmwasm_std::OverflowOperation::Add;
+use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, StdError, Storage, Coin, Uint128, Addr, CosmosMsg, BankMsg, Timestamp};
 use cosmwasm_std::{coin, coins};
 use cw2::set_contract_version;
-
-use rsa::{PublicKey, RsaPrivateKey, PaddingScheme};
-use rand::rngs::OsRng;
+use cw_storage_plus::{Bound, U32Key};
 
 use crate::error::ContractError;
-use crate::msg::{AddressesResponse, BalanceResponse, ExecuteMsg, GoodsResponse, InstantiateMsg, OrderDetailResponse, OrdersResponse, QueryMsg};
+use crate::msg::{CartItem, PostItem, ResetItem, TakeOrderItem, AddressesResponse, InsurancePoolResponse, ReputationResponse, ReviewsResponse, KeyHistoryResponse, KeyResponse, MessageRecipient, MessagesResponse, MessageView, ReassignmentsResponse, Viewer, ShippingEstimate, ShippingEstimatesResponse, TrackingResponse, BalanceResponse, ClaimableResponse, ExecuteMsg, GoodsResponse, InstantiateMsg, OffersResponse, OrderDetailResponse, OrdersResponse, PriceResponse, QueryMsg, ShipperResponse, ShippersResponse, StoreResponse, CategoriesResponse};
 
-use crate::state::{State, STATE, Goods, GoodsStatus, GOODS_LIST, ORDER_LIST, Order, OrderStatus, ShipperBid};
-use crate::helper::{assert_sent_sufficient_coin, merge_coin};
-// use serde::de::Unexpected::Map;
+use crate::reputation::{self, ReputationEvent};
+use crate::state::{State, STATE, Goods, GoodsStatus, GOODS_LIST, ORDER_LIST, Order, OrderStatus, ShipperBid, CLAIMABLE, Listing, EnglishAuction, DutchAuction, AuctionBid, Offer, OfferStatus, OFFER_LIST, BiddingMode, SealedBidding, BidCommitment, AutoSelect, SelectionPolicy, Shipper, SHIPPERS, SHIPPERS_BY_AREA, Location, ShippingLeg, Checkpoint, CheckpointKind, TRACKING, INSURANCE_POOL, AddressEnvelope, EncryptionAlgorithm, PERMIT_KEYS, Reassignment, ReassignmentProposal, REASSIGNMENTS, Message, MessageEntry, MESSAGES, UserKey, USER_KEYS, KEY_HISTORY, Review, Role, REVIEWS, ReturnPolicy, Store, STORES, STORE_GOODS, GoodsMetadata, CATEGORIES, GOODS_BY_CATEGORY, GOODS_BY_TAG, OrderItem, default_collateral_bps, default_insurance_bps, default_max_batch_size};
+use crate::helper::{assert_sent_sufficient_coin, assert_valid_envelope, assert_valid_pub_key, bid_commitment, credit_claimable, distance_m, dutch_auction_price, is_valid_location, is_valid_permit_key, permit_message, sha256};
 use crate::state::GoodsStatus::{Available, Ordered, Returned, Sold};
 use cosmwasm_std::Order::Ascending;
-use crate::ContractError::Unauthorized;
 use crate::state::OrderStatus::{Bidding, Confirmed, Disputed, DisputingBroken, DisputingUnsatisfied, Setup, Shipping, WaitingAddressUpload};
 
+// upper bound on the checkpoints stored per order
+const MAX_CHECKPOINTS: usize = 64;
+const MAX_MESSAGES: usize = 100;
+const MAX_MESSAGE_BYTES: usize = 1024;
+const REVIEW_PERIOD: u64 = 30 * 24 * 3600;
+const DELIVERY_PROOF_PERIOD: u64 = 7 * 24 * 3600;
+const CONTEST_PERIOD: u64 = 3 * 24 * 3600;
+const DEFAULT_PAGE_LIMIT: u32 = 10;
+const MAX_PAGE_LIMIT: u32 = 30;
+const MAX_TAGS: usize = 16;
+const MAX_IMAGES: usize = 8;
+const MAX_METADATA_LEN: usize = 256;
+const MAX_CART_ITEMS: usize = 20;
+
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:defi_ecommerce";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -33,7 +42,13 @@ pub fn instantiate(
 ) -> Result<Response, ContractError> {
     let state = State {
         order_cnt: 0,
+        offer_cnt: 0,
         owner: info.sender.clone(),
+        arbiter: info.sender.clone(),
+        min_shipper_stake: None,
+        collateral_bps: default_collateral_bps(),
+        insurance_bps: default_insurance_bps(),
+        max_batch_size: default_max_batch_size(),
     };
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     STATE.save(deps.storage, &state)?;
@@ -46,403 +61,4595 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::Post {name, price, denom, seller_area} => try_post(deps, info, &name, price, &denom, &seller_area),
-        ExecuteMsg::Buy {name, buyer_area} => try_buy(deps, info, &name, &buyer_area),
-        ExecuteMsg::Reset {name, price} => try_reset(deps, info, &name, price),
-        ExecuteMsg::TakeOrder { id, pub_key, price} => try_take_order(deps, info, id, pub_key, price),
-        ExecuteMsg::ChooseBid {id, shipper} => try_choose_bid(deps, info, id, shipper),
-        ExecuteMsg::UploadAddress { id, address_enc } => try_upload_address(deps, info, id, address_enc),
-        ExecuteMsg::Confirm { id } => try_confirm(deps, info, id),
-        ExecuteMsg::DisputeBroken { id } => try_dispute_broken(deps, info, id),
-        ExecuteMsg::DisputeUnsatisfied { id } => try_dispute_unsatisfied(deps, info, id),
-        ExecuteMsg::DisputeConfirm { id} => try_dispute_confirm(deps, info, id)
+        ExecuteMsg::UpdateConfig {min_shipper_stake, collateral_bps, insurance_bps, arbiter, max_batch_size} => try_update_config(deps, info, min_shipper_stake, collateral_bps, insurance_bps, arbiter, max_batch_size),
+        ExecuteMsg::SetPermitKey {pub_key} => try_set_permit_key(deps, info, pub_key),
+        ExecuteMsg::FundInsurance {} => try_fund_insurance(deps, info),
+        ExecuteMsg::AddCategory {category} => try_add_category(deps, info, category),
+        ExecuteMsg::RemoveCategory {category} => try_remove_category(deps, info, category),
+        ExecuteMsg::SetStore {name, description_hash, default_area, return_policy, payout_address, accepted_denoms, shipping_subsidy} => try_set_store(deps, info, name, description_hash, default_area, return_policy, payout_address, accepted_denoms, shipping_subsidy),
+        ExecuteMsg::Post {name, price, denom, seller_area} => try_post(deps, env, info, &name, price, &denom, &seller_area),
+        ExecuteMsg::PostAuction {name, reserve_price, min_increment, denom, seller_area, end_time} => try_post_auction(deps, env, info, &name, reserve_price, min_increment, &denom, &seller_area, end_time),
+        ExecuteMsg::PostDutchAuction {name, start_price, floor_price, denom, seller_area, duration} => try_post_dutch_auction(deps, env, info, &name, start_price, floor_price, &denom, &seller_area, duration),
+        ExecuteMsg::Bid {name, buyer_area} => try_bid(deps, env, info, &name, &buyer_area),
+        ExecuteMsg::Finalize {name} => try_finalize(deps, env, &name),
+        ExecuteMsg::Buy {name, buyer_area, delivery_lock} => try_buy(deps, env, info, &name, &buyer_area, delivery_lock),
+        ExecuteMsg::Checkout {items, buyer_area, delivery_lock} => try_checkout(deps, env, info, items, &buyer_area, delivery_lock),
+        ExecuteMsg::Reset {name, price} => try_reset(deps, env, info, &name, price),
+        ExecuteMsg::BatchPost {items} => try_batch_post(deps, env, info, items),
+        ExecuteMsg::BatchReset {items} => try_batch_reset(deps, env, info, items),
+        ExecuteMsg::MakeOffer {name, buyer_area, expires_in} => try_make_offer(deps, env, info, &name, &buyer_area, expires_in),
+        ExecuteMsg::AcceptOffer {name, offer_id} => try_accept_offer(deps, env, info, &name, offer_id),
+        ExecuteMsg::RejectOffer {name, offer_id} => try_reject_offer(deps, info, &name, offer_id),
+        ExecuteMsg::CounterOffer {name, offer_id, price, expires_in} => try_counter_offer(deps, env, info, &name, offer_id, price, expires_in),
+        ExecuteMsg::AcceptCounterOffer {name, offer_id} => try_accept_counter_offer(deps, env, info, &name, offer_id),
+        ExecuteMsg::CancelOffer {name, offer_id} => try_cancel_offer(deps, info, &name, offer_id),
+        ExecuteMsg::SetGoodsLocation {name, location} => try_set_goods_location(deps, info, &name, location),
+        ExecuteMsg::SetGoodsStock {name, stock} => try_set_goods_stock(deps, info, &name, stock),
+        ExecuteMsg::SetGoodsMetadata {name, category, tags, description_hash, image_uris} => try_set_goods_metadata(deps, info, &name, category, tags, description_hash, image_uris),
+        ExecuteMsg::SetBuyerLocation {id, location} => try_set_buyer_location(deps, info, id, location),
+        ExecuteMsg::RegisterShipper {service_areas, pub_key, key_algorithm, capacity, rate_per_km, max_radius_km} => try_register_shipper(deps, info, service_areas, pub_key, key_algorithm.unwrap_or_default(), capacity, rate_per_km, max_radius_km),
+        ExecuteMsg::UnregisterShipper {} => try_unregister_shipper(deps, info),
+        ExecuteMsg::TakeOrder { id, pub_key, algorithm, price} => try_take_order(deps, info, id, pub_key, algorithm, price),
+        ExecuteMsg::BatchTakeOrder {items} => try_batch_take_order(deps, info, items),
+        ExecuteMsg::SealBidding {id, commit_period, reveal_period, deposit} => try_seal_bidding(deps, env, info, id, commit_period, reveal_period, deposit),
+        ExecuteMsg::UnsealBidding {id} => try_unseal_bidding(deps, env, info, id),
+        ExecuteMsg::CommitBid {id, commitment} => try_commit_bid(deps, env, info, id, commitment),
+        ExecuteMsg::RevealBid {id, pub_key, algorithm, price, salt} => try_reveal_bid(deps, env, info, id, pub_key, algorithm.unwrap_or_default(), price, &salt),
+        ExecuteMsg::ChooseBid {id, shipper} => try_choose_bid(deps, env, info, id, shipper),
+        ExecuteMsg::ChooseRoute {id, shippers} => try_choose_route(deps, env, info, id, shippers),
+        ExecuteMsg::ConfirmHandoff {id} => try_confirm_handoff(deps, info, id),
+        ExecuteMsg::ReportHandoffDamage {id} => try_report_handoff_damage(deps, env, info, id),
+        ExecuteMsg::ContestDamage {id} => try_contest_damage(deps, env, info, id),
+        ExecuteMsg::ResolveDamage {id, upheld} => try_resolve_damage(deps, env, info, id, upheld),
+        ExecuteMsg::AutoSelect {id, bidding_period, policy, max_fee} => try_auto_select(deps, env, info, id, bidding_period, policy, max_fee),
+        ExecuteMsg::SelectBid {id} => try_select_bid(deps, env, id),
+        ExecuteMsg::CancelAutoSelect {id} => try_cancel_auto_select(deps, env, info, id),
+        ExecuteMsg::UploadAddress { id, address_enc } => try_upload_address(deps, env, info, id, address_enc),
+        ExecuteMsg::UploadAddressEnvelope { id, envelope } => try_upload_address_envelope(deps, env, info, id, envelope),
+        ExecuteMsg::ReassignShipper { id, shipper } => try_reassign_shipper(deps, info, id, shipper),
+        ExecuteMsg::AcceptReassignment { id } => try_accept_reassignment(deps, env, info, id),
+        ExecuteMsg::RegisterKey { pub_key, algorithm } => try_register_key(deps, env, info, pub_key, algorithm.unwrap_or_default()),
+        ExecuteMsg::RevokeKey {} => try_revoke_key(deps, env, info),
+        ExecuteMsg::SendMessage { id, recipients } => try_send_message(deps, env, info, id, recipients),
+        ExecuteMsg::PostCheckpoint { id, kind, note_enc } => try_post_checkpoint(deps, env, info, id, kind, note_enc),
+        ExecuteMsg::SetDeliveryLock { id, hash } => try_set_delivery_lock(deps, info, id, hash),
+        ExecuteMsg::ProveDelivery { id, preimage } => try_prove_delivery(deps, env, info, id, preimage),
+        ExecuteMsg::Confirm { id } => try_confirm(deps, env, info, id),
+        ExecuteMsg::DisputeBroken { id, items } => try_dispute_broken(deps, env, info, id, items),
+        ExecuteMsg::DisputeUnsatisfied { id, items } => try_dispute_unsatisfied(deps, env, info, id, items),
+        ExecuteMsg::DisputeConfirm { id} => try_dispute_confirm(deps, env, info, id),
+        ExecuteMsg::Review { id, subject, rating, review_hash } => try_review(deps, env, info, id, subject, rating, review_hash),
+        ExecuteMsg::Withdraw { denoms } => try_withdraw(deps, info, denoms)
         // _ => unimplemented!()
 
     }
 }
 
-pub fn try_post(deps: DepsMut, info: MessageInfo, name: &str, price: u32, denom: &str, seller_area: &str) -> Result<Response, ContractError> {
+pub fn try_update_config(deps: DepsMut, info: MessageInfo, min_shipper_stake: Option<Coin>, collateral_bps: Option<u64>, insurance_bps: Option<u64>, arbiter: Option<String>, max_batch_size: Option<u32>) -> Result<Response, ContractError> {
+    let arbiter = arbiter.map(|x| deps.api.addr_validate(&x)).transpose()?;
+    STATE.update(deps.storage, |mut state| -> Result<_, ContractError> {
+        if info.sender != state.owner {
+            return Err(ContractError::Unauthorized {});
+        }
+        if let Some(min_stake) = min_shipper_stake {
+            state.min_shipper_stake = if min_stake.amount.is_zero() { None } else { Some(min_stake) };
+        }
+        if let Some(collateral_bps) = collateral_bps {
+            state.collateral_bps = collateral_bps;
+        }
+        if let Some(insurance_bps) = insurance_bps {
+            if insurance_bps > 10_000 {
+                return Err(ContractError::InvalidConfig {});
+            }
+            state.insurance_bps = insurance_bps;
+        }
+        if let Some(arbiter) = arbiter {
+            state.arbiter = arbiter;
+        }
+        if let Some(max_batch_size) = max_batch_size {
+            if max_batch_size == 0 {
+                return Err(ContractError::InvalidConfig {});
+            }
+            state.max_batch_size = max_batch_size;
+        }
+        Ok(state)
+    })?;
+    Ok(Response::new().add_attribute("method", "try_update_config"))
+}
+
+pub fn try_set_permit_key(deps: DepsMut, info: MessageInfo, pub_key: Binary) -> Result<Response, ContractError> {
+    if !is_valid_permit_key(pub_key.as_slice()) {
+        return Err(ContractError::InvalidPublicKey {});
+    }
+    PERMIT_KEYS.save(deps.storage, &info.sender, &pub_key)?;
+    Ok(Response::new().add_attribute("method", "try_set_permit_key"))
+}
+
+pub fn try_fund_insurance(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    for c in info.funds.iter() {
+        credit_insurance_pool(deps.storage, c)?;
+    }
+    Ok(Response::new().add_attribute("method", "try_fund_insurance"))
+}
+
+pub fn try_add_category(deps: DepsMut, info: MessageInfo, category: String) -> Result<Response, ContractError> {
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if category.is_empty() || category.len() > MAX_METADATA_LEN {
+        return Err(ContractError::InvalidMetadata {});
+    }
+    CATEGORIES.save(deps.storage, &category, &true)?;
+    Ok(Response::new().add_attribute("method", "try_add_category"))
+}
+
+pub fn try_remove_category(deps: DepsMut, info: MessageInfo, category: String) -> Result<Response, ContractError> {
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !CATEGORIES.has(deps.storage, &category) {
+        return Err(ContractError::UnknownCategory {});
+    }
+    CATEGORIES.remove(deps.storage, &category);
+    Ok(Response::new().add_attribute("method", "try_remove_category"))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn try_set_store(deps: DepsMut, info: MessageInfo, name: String, description_hash: Binary, default_area: String, return_policy: ReturnPolicy, payout_address: Option<String>, accepted_denoms: Vec<String>, shipping_subsidy: Option<Coin>) -> Result<Response, ContractError> {
+    if name.is_empty() {
+        return Err(ContractError::InvalidStore {});
+    }
+    let payout_address = match payout_address {
+        Some(address) => deps.api.addr_validate(&address)?,
+        None => info.sender.clone()
+    };
+    let store = Store {
+        owner: info.sender.clone(),
+        name,
+        description_hash,
+        default_area,
+        return_policy,
+        payout_address,
+        accepted_denoms,
+        shipping_subsidy: shipping_subsidy.filter(|x| !x.amount.is_zero())
+    };
+    STORES.save(deps.storage, &info.sender, &store)?;
+    Ok(Response::new().add_attribute("method", "try_set_store"))
+}
+
+// Saves a new listing, linking it to the seller's store if there is one
+fn save_listing(storage: &mut dyn Storage, now: Timestamp, mut good: Goods) -> Result<(), ContractError> {
+    let old = GOODS_LIST.may_load(storage, &good.name)?;
+    if let Some(old) = &old {
+        refund_expired_offers(storage, &old.name, now)?;
+        // only the seller can post over a listing, and only while nobody has funds escrowed on it
+        // and no open order still holds some of its units
+        if old.seller != good.seller {
+            return Err(ContractError::Unauthorized {});
+        }
+        if old.status != Available || old.reserved > 0 || has_escrow(storage, old)? {
+            return Err(ContractError::GoodsNotAvailable {});
+        }
+    }
+    let store = STORES.may_load(storage, &good.seller)?;
+    if let Some(store) = &store {
+        if !store.accepted_denoms.is_empty() && !store.accepted_denoms.contains(&good.price.denom) {
+            return Err(ContractError::DenomNotAccepted {});
+        }
+    }
+    // posting under an existing name replaces that listing and its index entries
+    if let Some(old) = old {
+        unindex_metadata(storage, &old);
+        if let Some(owner) = &old.store {
+            STORE_GOODS.remove(storage, (owner, &old.name));
+        }
+    }
+    if let Some(store) = store {
+        if good.seller_area.is_empty() {
+            good.seller_area = store.default_area;
+        }
+        STORE_GOODS.save(storage, (&store.owner, &good.name), &true)?;
+        good.store = Some(store.owner);
+    }
+    GOODS_LIST.save(storage, &good.name, &good)?;
+    Ok(())
+}
+
+// Whether an auction bid or an open offer holds buyer funds on the listing
+fn has_escrow(storage: &dyn Storage, good: &Goods) -> StdResult<bool> {
+    if let Listing::EnglishAuction(EnglishAuction { highest_bid: Some(_), .. }) = &good.listing {
+        return Ok(true);
+    }
+    Ok(OFFER_LIST.prefix(&good.name).range(storage, None, None, Ascending).next().is_some())
+}
+
+fn unindex_metadata(storage: &mut dyn Storage, good: &Goods) {
+    if let Some(category) = &good.metadata.category {
+        GOODS_BY_CATEGORY.remove(storage, (category, &good.name));
+    }
+    for tag in good.metadata.tags.iter() {
+        GOODS_BY_TAG.remove(storage, (tag, &good.name));
+    }
+}
+
+// Sale proceeds go to the payout address of the store the goods were listed in
+fn seller_payout(storage: &dyn Storage, order: &Order) -> StdResult<Addr> {
+    Ok(order_store(storage, order)?.map(|x| x.payout_address).unwrap_or_else(|| order.seller.clone()))
+}
+
+// Part of the shipping fee the store takes over from the buyer, never more than the price
+fn shipping_subsidy(storage: &dyn Storage, order: &Order) -> StdResult<Uint128> {
+    let cap = match order_store(storage, order)?.and_then(|x| x.shipping_subsidy) {
+        Some(cap) => cap,
+        None => return Ok(Uint128::zero())
+    };
+    if cap.denom != order.price.denom || order.shipping_fee.denom != order.price.denom {
+        return Ok(Uint128::zero());
+    }
+    Ok(cap.amount.min(order.shipping_fee.amount).min(order.price.amount))
+}
+
+fn order_store(storage: &dyn Storage, order: &Order) -> StdResult<Option<Store>> {
+    match &order.goods.store {
+        Some(owner) => STORES.may_load(storage, owner),
+        None => Ok(None)
+    }
+}
+
+pub fn try_post(deps: DepsMut, env: Env, info: MessageInfo, name: &str, price: u32, denom: &str, seller_area: &str) -> Result<Response, ContractError> {
     let good = Goods {
         name: String::from(name),
         seller: info.sender,
         price: coin(Uint128::from(price).u128(), String::from(denom)),
         seller_area: String::from(seller_area),
-        status: GoodsStatus::Available
+        location: None,
+        listing: Listing::FixedPrice,
+        status: GoodsStatus::Available,
+        store: None,
+        metadata: GoodsMetadata::default(),
+        stock: 1,
+        reserved: 0
     };
-    GOODS_LIST.save(deps.storage, name, &good)?;
+    save_listing(deps.storage, env.block.time, good)?;
     Ok(Response::new().add_attribute("method", "try_post"))
 }
 
-pub fn try_buy(deps: DepsMut, info: MessageInfo, name: &str, buyer_area: &str) -> Result<Response, ContractError> {
+pub fn try_set_goods_location(deps: DepsMut, info: MessageInfo, name: &str, location: Location) -> Result<Response, ContractError> {
     let mut good = GOODS_LIST.load(deps.storage, name)?;
+    if good.seller != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
     if good.status != Available {
         return Err(ContractError::GoodsNotAvailable {});
     }
-    assert_sent_sufficient_coin(&info.funds, vec![good.clone().price])?;
-    good.status = Ordered;
-    let update_good = |d: Option<Goods>| -> StdResult<Goods> {
-        match d {
-            Some(_) => Ok(good.clone()),
-            None => unimplemented!(),
-        }
-    };
-    GOODS_LIST.update(deps.storage, name, update_good)?;
-    let order = Order {
-        id: STATE.load(deps.storage)?.order_cnt.clone(),
-        buyer: info.sender.clone(),
-        seller: good.clone().seller,
-        goods: good.clone(),
-        price: good.clone().price,
-        buyer_area: String::from(buyer_area),
-        shipper_bids: vec![],
-        shipping_fee: Default::default(),
-        shipper: Addr::unchecked("Dummy_Shipper"),
-        shipper_key: Default::default(),
-        buyer_addr_enc: Default::default(),
-        seller_addr_enc: Default::default(),
-        status: OrderStatus::Setup
-    };
-    STATE.update(deps.storage, |mut state| -> Result<_, ContractError> {
-        state.order_cnt += 1;
-        Ok(state)
-    })?;
-    ORDER_LIST.save(deps.storage, &order.id.to_string(), &order)?;
-
-    Ok(Response::new().add_attribute("method", "try_buy"))
+    if !is_valid_location(&location) {
+        return Err(ContractError::InvalidLocation {});
+    }
+    good.location = Some(location);
+    GOODS_LIST.save(deps.storage, name, &good)?;
+    Ok(Response::new().add_attribute("method", "try_set_goods_location"))
 }
 
-pub fn try_reset(deps: DepsMut, info: MessageInfo, name: &str, price: u32) -> Result<Response, ContractError> {
+// Only fixed-price listings can sell more than one unit
+pub fn try_set_goods_stock(deps: DepsMut, info: MessageInfo, name: &str, stock: u32) -> Result<Response, ContractError> {
     let mut good = GOODS_LIST.load(deps.storage, name)?;
+    if good.seller != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
     if good.status != Available {
         return Err(ContractError::GoodsNotAvailable {});
     }
+    if stock == 0 {
+        return Err(ContractError::InsufficientStock {});
+    }
+    if stock > 1 && good.listing != Listing::FixedPrice {
+        return Err(ContractError::WrongListingType {});
+    }
+    good.stock = stock;
+    GOODS_LIST.save(deps.storage, name, &good)?;
+    Ok(Response::new().add_attribute("method", "try_set_goods_stock"))
+}
+
+pub fn try_set_goods_metadata(deps: DepsMut, info: MessageInfo, name: &str, category: Option<String>, tags: Vec<String>, description_hash: Option<Binary>, image_uris: Vec<String>) -> Result<Response, ContractError> {
+    let mut good = GOODS_LIST.load(deps.storage, name)?;
     if good.seller != info.sender {
         return Err(ContractError::Unauthorized {});
     }
-    good.price.amount = Uint128::from(price);
-    let update_good = |d: Option<Goods>| -> StdResult<Goods> {
-        match d {
-            Some(_) => Ok(good.clone()),
-            None => unimplemented!(),
+    if good.status != Available {
+        return Err(ContractError::GoodsNotAvailable {});
+    }
+    // a listing may keep a category the owner removed since, but can not move into one
+    if let Some(category) = &category {
+        if good.metadata.category.as_ref() != Some(category) && !CATEGORIES.has(deps.storage, category) {
+            return Err(ContractError::UnknownCategory {});
         }
-    };
-    GOODS_LIST.update(deps.storage, name, update_good)?;
-    Ok(Response::new().add_attribute("method", "try_reset"))
+    }
+    let mut tags: Vec<String> = tags.iter().map(|x| x.to_lowercase()).collect();
+    tags.sort();
+    tags.dedup();
+    if tags.len() > MAX_TAGS || image_uris.len() > MAX_IMAGES
+        || tags.iter().chain(image_uris.iter()).any(|x| x.is_empty() || x.len() > MAX_METADATA_LEN) {
+        return Err(ContractError::InvalidMetadata {});
+    }
+    unindex_metadata(deps.storage, &good);
+    if let Some(category) = &category {
+        GOODS_BY_CATEGORY.save(deps.storage, (category, name), &true)?;
+    }
+    for tag in tags.iter() {
+        GOODS_BY_TAG.save(deps.storage, (tag, name), &true)?;
+    }
+    good.metadata = GoodsMetadata { category, tags, description_hash, image_uris };
+    GOODS_LIST.save(deps.storage, name, &good)?;
+    Ok(Response::new().add_attribute("method", "try_set_goods_metadata"))
 }
 
-pub fn try_take_order(deps: DepsMut, info: MessageInfo, id: u32, pub_key: String, price: Coin) -> Result<Response, ContractError> {
-    let mut order = ORDER_LIST.load(deps.storage, &id.to_string())?;
+pub fn try_set_buyer_location(deps: DepsMut, info: MessageInfo, id: u32, location: Location) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.buyer != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
     if order.status != Setup && order.status != Bidding {
         return Err(ContractError::OrderNotAvailable {});
     }
-    assert_sent_sufficient_coin(&info.funds, vec![order.clone().price])?;
-    order.status = Bidding;
-    let bid = ShipperBid {
-        shipper: info.sender,
-        pub_key: pub_key,
-        price: price
-    };
-    order.shipper_bids.push(bid);
-    let update_order = |d: Option<Order>| -> StdResult<Order> {
-        match d {
-            Some(_) => Ok(order.clone()),
-            None => unimplemented!(),
-        }
-    };
-    ORDER_LIST.update(deps.storage, &id.to_string(), update_order)?;
+    if !is_valid_location(&location) {
+        return Err(ContractError::InvalidLocation {});
+    }
+    order.buyer_location = Some(location);
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new().add_attribute("method", "try_set_buyer_location"))
+}
 
-    Ok(Response::new().add_attribute("method", "try_take_order"))
+#[allow(clippy::too_many_arguments)]
+pub fn try_post_auction(deps: DepsMut, env: Env, info: MessageInfo, name: &str, reserve_price: u32, min_increment: u32, denom: &str, seller_area: &str, end_time: u64) -> Result<Response, ContractError> {
+    let end_time = Timestamp::from_seconds(end_time);
+    if end_time <= env.block.time {
+        return Err(ContractError::InvalidEndTime {});
+    }
+    let good = Goods {
+        name: String::from(name),
+        seller: info.sender,
+        price: coin(Uint128::from(reserve_price).u128(), String::from(denom)),
+        seller_area: String::from(seller_area),
+        location: None,
+        listing: Listing::EnglishAuction(EnglishAuction {
+            reserve_price: Uint128::from(reserve_price),
+            min_increment: Uint128::from(min_increment),
+            end_time,
+            highest_bid: None
+        }),
+        status: GoodsStatus::Available,
+        store: None,
+        metadata: GoodsMetadata::default(),
+        stock: 1,
+        reserved: 0
+    };
+    save_listing(deps.storage, env.block.time, good)?;
+    Ok(Response::new().add_attribute("method", "try_post_auction"))
 }
 
-pub fn try_choose_bid(deps: DepsMut, info: MessageInfo, id: u32, shipper: String) -> Result<Response, ContractError> {
-    let mut order = ORDER_LIST.load(deps.storage, &id.to_string())?;
-    if order.status != Bidding {
-        return Err(ContractError::OrderNotAvailable {});
+#[allow(clippy::too_many_arguments)]
+pub fn try_post_dutch_auction(deps: DepsMut, env: Env, info: MessageInfo, name: &str, start_price: u32, floor_price: u32, denom: &str, seller_area: &str, duration: u64) -> Result<Response, ContractError> {
+    if floor_price > start_price {
+        return Err(ContractError::InvalidPrice {});
     }
-    if info.sender != order.buyer {
-        return Err(ContractError::Unauthorized {});
+    if duration == 0 {
+        return Err(ContractError::InvalidEndTime {});
     }
-    match order.shipper_bids.iter().find(|x| x.shipper == Addr::unchecked(shipper.clone())) {
-        Some(x) => {
-            assert_sent_sufficient_coin(&info.funds, coins(x.price.clone().amount.checked_mul(Uint128::from(2u32)).unwrap().u128(), x.price.clone().denom));
-            order.status = WaitingAddressUpload;
-            order.shipper = x.shipper.clone();
-            order.shipper_key = x.pub_key.clone();
-            order.shipping_fee = x.price.clone();
-            let update_order = |d: Option<Order>| -> StdResult<Order> {
-                match d {
-                    Some(_) => Ok(order.clone()),
-                    None => unimplemented!(),
-                }
-            };
-            ORDER_LIST.update(deps.storage, &id.to_string(), update_order)?;
+    let good = Goods {
+        name: String::from(name),
+        seller: info.sender,
+        price: coin(Uint128::from(start_price).u128(), String::from(denom)),
+        seller_area: String::from(seller_area),
+        location: None,
+        listing: Listing::DutchAuction(DutchAuction {
+            start_price: Uint128::from(start_price),
+            floor_price: Uint128::from(floor_price),
+            start_time: env.block.time,
+            end_time: env.block.time.plus_seconds(duration)
+        }),
+        status: GoodsStatus::Available,
+        store: None,
+        metadata: GoodsMetadata::default(),
+        stock: 1,
+        reserved: 0
+    };
+    save_listing(deps.storage, env.block.time, good)?;
+    Ok(Response::new().add_attribute("method", "try_post_dutch_auction"))
+}
 
-            Ok(Response::new().add_attribute("method", "try_choose_bid"))
-        }
-        None => {
-            Err(ContractError::ShipperNotFound {})
+pub fn try_buy(deps: DepsMut, env: Env, info: MessageInfo, name: &str, buyer_area: &str, delivery_lock: Option<Binary>) -> Result<Response, ContractError> {
+    assert_valid_delivery_lock(&delivery_lock)?;
+    let good = GOODS_LIST.load(deps.storage, name)?;
+    if good.status != Available {
+        return Err(ContractError::GoodsNotAvailable {});
+    }
+    let price = match &good.listing {
+        Listing::FixedPrice => good.clone().price,
+        Listing::DutchAuction(auction) => coin(dutch_auction_price(auction, env.block.time).u128(), good.price.denom.clone()),
+        Listing::EnglishAuction(_) => return Err(ContractError::WrongListingType {})
+    };
+    assert_sent_sufficient_coin(&info.funds, vec![price.clone()])?;
+    if let Listing::DutchAuction(_) = good.listing {
+        // the price may have dropped since the buyer signed, refund whatever was sent above it
+        let sent = info.funds.iter().find(|c| c.denom == price.denom).map(|c| c.amount).unwrap_or_default();
+        if sent > price.amount {
+            credit_claimable(deps.storage, &info.sender, &coins((sent - price.amount).u128(), price.denom.clone()))?;
         }
     }
+    let mut order = create_order(deps.storage, info.sender, good, price, buyer_area)?;
+    if delivery_lock.is_some() {
+        order.delivery_lock = delivery_lock;
+        ORDER_LIST.save(deps.storage, &order.id.to_string(), &order)?;
+    }
+
+    Ok(Response::new().add_attribute("method", "try_buy"))
 }
 
-pub fn try_upload_address(deps: DepsMut, info: MessageInfo, id: u32, address_enc: Vec<u8>) -> Result<Response, ContractError> {
-    let mut order = ORDER_LIST.load(deps.storage, &id.to_string())?;
-    if order.status != WaitingAddressUpload {
-        return Err(ContractError::OrderNotAvailable {});
-    }
-    if order.buyer == info.sender {
-        order.buyer_addr_enc = address_enc;
+pub fn try_checkout(deps: DepsMut, env: Env, info: MessageInfo, items: Vec<CartItem>, buyer_area: &str, delivery_lock: Option<Binary>) -> Result<Response, ContractError> {
+    if items.is_empty() || items.len() > MAX_CART_ITEMS {
+        return Err(ContractError::InvalidCart {});
     }
-    else if order.seller == info.sender {
-        order.seller_addr_enc = address_enc;
+    assert_valid_delivery_lock(&delivery_lock)?;
+    let mut goods: Vec<Goods> = vec![];
+    let mut order_items: Vec<OrderItem> = vec![];
+    let mut total = Uint128::zero();
+    for item in items {
+        if item.quantity == 0 || order_items.iter().any(|x| x.name == item.name) {
+            return Err(ContractError::InvalidCart {});
+        }
+        let good = GOODS_LIST.load(deps.storage, &item.name)?;
+        if good.status != Available {
+            return Err(ContractError::GoodsNotAvailable {});
+        }
+        if good.listing != Listing::FixedPrice {
+            return Err(ContractError::WrongListingType {});
+        }
+        // everything in the cart ships together and is paid in one coin
+        if let Some(first) = goods.first() {
+            if good.seller != first.seller || good.price.denom != first.price.denom {
+                return Err(ContractError::InvalidCart {});
+            }
+        }
+        if good.stock < item.quantity {
+            return Err(ContractError::InsufficientStock {});
+        }
+        let amount = good.price.amount.checked_mul(Uint128::from(item.quantity)).map_err(StdError::from)?;
+        total = total.checked_add(amount).map_err(StdError::from)?;
+        order_items.push(OrderItem { name: item.name, quantity: item.quantity, unit_price: good.price.clone() });
+        goods.push(good);
     }
-    else {
-        return Err(ContractError::Unauthorized {});
+    let price = coin(total.u128(), goods[0].price.denom.clone());
+    assert_sent_sufficient_coin(&info.funds, vec![price.clone()])?;
+    for (good, item) in goods.iter_mut().zip(order_items.iter()) {
+        refund_expired_offers(deps.storage, &good.name, env.block.time)?;
+        reserve_goods(deps.storage, good, item.quantity)?;
     }
-    if !order.buyer_addr_enc.is_empty() && !order.seller_addr_enc.is_empty() {
-        order.status = Shipping;
+    let mut order = open_order(deps.storage, info.sender, goods.swap_remove(0), price, buyer_area, order_items)?;
+    if delivery_lock.is_some() {
+        order.delivery_lock = delivery_lock;
+        ORDER_LIST.save(deps.storage, &order.id.to_string(), &order)?;
     }
-    let update_order = |d: Option<Order>| -> StdResult<Order> {
-        match d {
-            Some(_) => Ok(order.clone()),
-            None => unimplemented!(),
-        }
-    };
-    ORDER_LIST.update(deps.storage, &id.to_string(), update_order)?;
-    Ok(Response::new().add_attribute("method", "try_upload_address"))
+    Ok(Response::new()
+        .add_attribute("method", "try_checkout")
+        .add_attribute("order_id", order.id.to_string()))
 }
 
-pub fn try_confirm(deps: DepsMut, info: MessageInfo, id: u32) -> Result<Response, ContractError> {
-    let mut order = ORDER_LIST.load(deps.storage, &id.to_string())?;
-    if order.status != Shipping {
-        return Err(ContractError::OrderNotAvailable {});
+pub fn try_bid(deps: DepsMut, env: Env, info: MessageInfo, name: &str, buyer_area: &str) -> Result<Response, ContractError> {
+    let mut good = GOODS_LIST.load(deps.storage, name)?;
+    if good.status != Available {
+        return Err(ContractError::GoodsNotAvailable {});
     }
-    if order.buyer != info.sender {
+    if good.seller == info.sender {
         return Err(ContractError::Unauthorized {});
     }
-    order.status = Confirmed;
-    let mut good = GOODS_LIST.load(deps.storage, &order.goods.name)?;
-    good.status = Sold;
-    order.goods = good.clone();
-    let update_good = |d: Option<Goods>| -> StdResult<Goods> {
-        match d {
-            Some(_) => Ok(good.clone()),
-            None => unimplemented!(),
-        }
+    let mut auction = match good.listing.clone() {
+        Listing::EnglishAuction(auction) => auction,
+        _ => return Err(ContractError::WrongListingType {})
     };
-    let update_order = |d: Option<Order>| -> StdResult<Order> {
-        match d {
-            Some(_) => Ok(order.clone()),
-            None => unimplemented!(),
-        }
+    if env.block.time >= auction.end_time {
+        return Err(ContractError::AuctionEnded {});
+    }
+    let amount = info.funds.iter()
+        .find(|c| c.denom == good.price.denom)
+        .map(|c| c.amount)
+        .unwrap_or_default();
+    let min_bid = match &auction.highest_bid {
+        Some(highest) => highest.amount.amount.checked_add(auction.min_increment).map_err(StdError::from)?,
+        None => auction.reserve_price
     };
-    ORDER_LIST.update(deps.storage, &id.to_string(), update_order)?;
-    GOODS_LIST.update(deps.storage, &order.goods.name, update_good)?;
-    Ok(Response::new()
-        .add_attribute("method", "try_confirm")
-        .add_message(CosmosMsg::Bank(BankMsg::Send { to_address: order.seller.into_string(), amount: vec![order.price] }))
-        .add_message(CosmosMsg::Bank(BankMsg::Send { to_address: order.shipper.into_string(), amount: vec![order.shipping_fee] })))
+    if amount.is_zero() || amount < min_bid {
+        return Err(ContractError::BidTooLow {});
+    }
+    // the outbid bidder gets their escrow back as a claimable balance
+    if let Some(highest) = auction.highest_bid {
+        credit_claimable(deps.storage, &highest.bidder, &[highest.amount])?;
+    }
+    auction.highest_bid = Some(AuctionBid {
+        bidder: info.sender,
+        amount: coin(amount.u128(), good.price.denom.clone()),
+        buyer_area: String::from(buyer_area)
+    });
+    good.listing = Listing::EnglishAuction(auction);
+    GOODS_LIST.save(deps.storage, name, &good)?;
+    Ok(Response::new().add_attribute("method", "try_bid"))
 }
 
-pub fn try_dispute_broken(deps: DepsMut, info: MessageInfo, id: u32) -> Result<Response, ContractError> {
-    let mut order = ORDER_LIST.load(deps.storage, &id.to_string())?;
-    if order.status != Shipping {
-        return Err(ContractError::OrderNotAvailable {});
+pub fn try_finalize(deps: DepsMut, env: Env, name: &str) -> Result<Response, ContractError> {
+    let mut good = GOODS_LIST.load(deps.storage, name)?;
+    if good.status != Available {
+        return Err(ContractError::GoodsNotAvailable {});
     }
-    if order.buyer != info.sender {
-        return Err(ContractError::Unauthorized {});
+    let auction = match good.listing.clone() {
+        Listing::EnglishAuction(auction) => auction,
+        _ => return Err(ContractError::WrongListingType {})
+    };
+    if env.block.time < auction.end_time {
+        return Err(ContractError::AuctionNotEnded {});
     }
-    order.status = DisputingBroken;
-    let update_order = |d: Option<Order>| -> StdResult<Order> {
-        match d {
-            Some(_) => Ok(order.clone()),
-            None => unimplemented!(),
+    match auction.highest_bid {
+        Some(winner) => {
+            create_order(deps.storage, winner.bidder, good, winner.amount, &winner.buyer_area)?;
         }
-    };
-    ORDER_LIST.update(deps.storage, &id.to_string(), update_order)?;
-    Ok(Response::new().add_attribute("method", "try_dispute_broken"))
+        None => {
+            // nobody met the reserve, the goods stay listed at the reserve price
+            good.listing = Listing::FixedPrice;
+            GOODS_LIST.save(deps.storage, name, &good)?;
+        }
+    }
+    Ok(Response::new().add_attribute("method", "try_finalize"))
 }
 
-pub fn try_dispute_unsatisfied(deps: DepsMut, info: MessageInfo, id: u32) -> Result<Response, ContractError> {
-    let mut order = ORDER_LIST.load(deps.storage, &id.to_string())?;
-    if order.status != Shipping {
-        return Err(ContractError::OrderNotAvailable {});
+pub fn try_make_offer(deps: DepsMut, env: Env, info: MessageInfo, name: &str, buyer_area: &str, expires_in: u64) -> Result<Response, ContractError> {
+    let good = GOODS_LIST.load(deps.storage, name)?;
+    if good.status != Available {
+        return Err(ContractError::GoodsNotAvailable {});
     }
-    if order.buyer != info.sender {
+    if good.listing != Listing::FixedPrice {
+        return Err(ContractError::WrongListingType {});
+    }
+    if good.seller == info.sender {
         return Err(ContractError::Unauthorized {});
     }
-    order.status = DisputingUnsatisfied;
-    let update_order = |d: Option<Order>| -> StdResult<Order> {
-        match d {
-            Some(_) => Ok(order.clone()),
-            None => unimplemented!(),
-        }
+    let amount = info.funds.iter()
+        .find(|c| c.denom == good.price.denom)
+        .map(|c| c.amount)
+        .unwrap_or_default();
+    if amount.is_zero() {
+        return Err(ContractError::InsufficientFundsSend {});
+    }
+    refund_expired_offers(deps.storage, name, env.block.time)?;
+    let offer = Offer {
+        id: STATE.load(deps.storage)?.offer_cnt,
+        goods_name: String::from(name),
+        buyer: info.sender,
+        price: coin(amount.u128(), good.price.denom),
+        counter_price: None,
+        buyer_area: String::from(buyer_area),
+        expires: env.block.time.plus_seconds(expires_in),
+        status: OfferStatus::Open
     };
-    ORDER_LIST.update(deps.storage, &id.to_string(), update_order)?;
-    Ok(Response::new().add_attribute("method", "try_dispute_unsatisfied"))
+    STATE.update(deps.storage, |mut state| -> Result<_, ContractError> {
+        state.offer_cnt += 1;
+        Ok(state)
+    })?;
+    OFFER_LIST.save(deps.storage, (name, U32Key::new(offer.id)), &offer)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_make_offer")
+        .add_attribute("offer_id", offer.id.to_string()))
 }
 
-pub fn try_dispute_confirm(deps: DepsMut, info: MessageInfo, id: u32) -> Result<Response, ContractError> {
-    let mut order = ORDER_LIST.load(deps.storage, &id.to_string())?;
-    if order.status != DisputingBroken && order.status != DisputingUnsatisfied {
-        return Err(ContractError::OrderNotAvailable {});
+pub fn try_accept_offer(deps: DepsMut, env: Env, info: MessageInfo, name: &str, offer_id: u32) -> Result<Response, ContractError> {
+    let good = GOODS_LIST.load(deps.storage, name)?;
+    if good.status != Available {
+        return Err(ContractError::GoodsNotAvailable {});
     }
-    if order.seller != info.sender {
+    if good.seller != info.sender {
         return Err(ContractError::Unauthorized {});
     }
-    let mut res = Response::new().add_attribute("method", "try_dispute_confirm");
-    res = match order.status {
-        DisputingBroken => {
-            res.add_message(CosmosMsg::Bank(BankMsg::Send { to_address: order.clone().buyer.into_string(), amount: merge_coin(vec![order.clone().price], coins(order.clone().shipping_fee.amount.checked_mul(Uint128::from(2u32)).unwrap().u128(), order.clone().shipping_fee.denom)) }))
-                .add_message(CosmosMsg::Bank(BankMsg::Send { to_address: order.clone().seller.into_string(), amount: vec![order.clone().price] }))
-        },
-        DisputingUnsatisfied => {
-            res.add_message(CosmosMsg::Bank(BankMsg::Send { to_address: order.clone().shipper.into_string(), amount: coins(order.clone().shipping_fee.amount.checked_mul(Uint128::from(2u32)).unwrap().u128(), order.clone().shipping_fee.denom) }))
-                .add_message(CosmosMsg::Bank(BankMsg::Send { to_address: order.clone().buyer.into_string(), amount: vec![order.clone().price] }))
-        },
-        _ => unimplemented!()
+    let offer = load_offer(deps.storage, name, offer_id)?;
+    if offer.status != OfferStatus::Open {
+        return Err(ContractError::OfferNotAvailable {});
+    }
+    if env.block.time >= offer.expires {
+        return Err(ContractError::OfferExpired {});
+    }
+    OFFER_LIST.remove(deps.storage, (name, U32Key::new(offer.id)));
+    create_order(deps.storage, offer.buyer, good, offer.price, &offer.buyer_area)?;
+    Ok(Response::new().add_attribute("method", "try_accept_offer"))
+}
+
+pub fn try_reject_offer(deps: DepsMut, info: MessageInfo, name: &str, offer_id: u32) -> Result<Response, ContractError> {
+    let good = GOODS_LIST.load(deps.storage, name)?;
+    if good.seller != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let offer = load_offer(deps.storage, name, offer_id)?;
+    OFFER_LIST.remove(deps.storage, (name, U32Key::new(offer.id)));
+    credit_claimable(deps.storage, &offer.buyer, &[offer.price])?;
+    Ok(Response::new().add_attribute("method", "try_reject_offer"))
+}
+
+pub fn try_counter_offer(deps: DepsMut, env: Env, info: MessageInfo, name: &str, offer_id: u32, price: u32, expires_in: u64) -> Result<Response, ContractError> {
+    let good = GOODS_LIST.load(deps.storage, name)?;
+    if good.seller != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if good.status != Available {
+        return Err(ContractError::GoodsNotAvailable {});
+    }
+    let mut offer = load_offer(deps.storage, name, offer_id)?;
+    if offer.status != OfferStatus::Open {
+        return Err(ContractError::OfferNotAvailable {});
+    }
+    // a counter at or below the escrowed amount should simply be accepted
+    if Uint128::from(price) <= offer.price.amount {
+        return Err(ContractError::InvalidPrice {});
+    }
+    offer.counter_price = Some(coin(Uint128::from(price).u128(), offer.price.denom.clone()));
+    offer.expires = env.block.time.plus_seconds(expires_in);
+    offer.status = OfferStatus::Countered;
+    OFFER_LIST.save(deps.storage, (name, U32Key::new(offer.id)), &offer)?;
+    Ok(Response::new().add_attribute("method", "try_counter_offer"))
+}
+
+pub fn try_accept_counter_offer(deps: DepsMut, env: Env, info: MessageInfo, name: &str, offer_id: u32) -> Result<Response, ContractError> {
+    let good = GOODS_LIST.load(deps.storage, name)?;
+    if good.status != Available {
+        return Err(ContractError::GoodsNotAvailable {});
+    }
+    let offer = load_offer(deps.storage, name, offer_id)?;
+    if offer.buyer != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let counter_price = match (&offer.status, &offer.counter_price) {
+        (OfferStatus::Countered, Some(counter_price)) => counter_price.clone(),
+        _ => return Err(ContractError::OfferNotAvailable {})
     };
-    order.status = Disputed;
-    let mut good = GOODS_LIST.load(deps.storage, &order.goods.name)?;
-    good.status = Returned;
-    order.goods = good.clone();
-    let update_good = |d: Option<Goods>| -> StdResult<Goods> {
-        match d {
-            Some(_) => Ok(good.clone()),
-            None => unimplemented!(),
+    if env.block.time >= offer.expires {
+        return Err(ContractError::OfferExpired {});
+    }
+    // the buyer tops up the difference between their escrow and the counter price
+    assert_sent_sufficient_coin(&info.funds, coins((counter_price.amount - offer.price.amount).u128(), counter_price.denom.clone()))?;
+    OFFER_LIST.remove(deps.storage, (name, U32Key::new(offer.id)));
+    create_order(deps.storage, offer.buyer, good, counter_price, &offer.buyer_area)?;
+    Ok(Response::new().add_attribute("method", "try_accept_counter_offer"))
+}
+
+pub fn try_cancel_offer(deps: DepsMut, info: MessageInfo, name: &str, offer_id: u32) -> Result<Response, ContractError> {
+    let offer = load_offer(deps.storage, name, offer_id)?;
+    if offer.buyer != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    OFFER_LIST.remove(deps.storage, (name, U32Key::new(offer.id)));
+    credit_claimable(deps.storage, &offer.buyer, &[offer.price])?;
+    Ok(Response::new().add_attribute("method", "try_cancel_offer"))
+}
+
+fn load_offer(storage: &dyn Storage, name: &str, offer_id: u32) -> Result<Offer, ContractError> {
+    OFFER_LIST.may_load(storage, (name, U32Key::new(offer_id)))?.ok_or(ContractError::OfferNotAvailable {})
+}
+
+// Removes every open offer on the goods and refunds their escrow.
+// Expired offers no longer bind anyone, their escrow goes back to the buyers
+fn refund_expired_offers(storage: &mut dyn Storage, name: &str, now: Timestamp) -> Result<(), ContractError> {
+    let offers: StdResult<Vec<_>> = OFFER_LIST.prefix(name).range(storage, None, None, Ascending).collect();
+    for (_, offer) in offers? {
+        if offer.expires <= now {
+            OFFER_LIST.remove(storage, (name, U32Key::new(offer.id)));
+            credit_claimable(storage, &offer.buyer, &[offer.price])?;
         }
+    }
+    Ok(())
+}
+
+fn close_offers(storage: &mut dyn Storage, name: &str) -> Result<(), ContractError> {
+    let offers: StdResult<Vec<_>> = OFFER_LIST.prefix(name).range(storage, None, None, Ascending).collect();
+    for (_, offer) in offers? {
+        OFFER_LIST.remove(storage, (name, U32Key::new(offer.id)));
+        credit_claimable(storage, &offer.buyer, &[offer.price])?;
+    }
+    Ok(())
+}
+
+// Opens a new order for one unit of the goods, the buyer's payment is already escrowed
+fn create_order(storage: &mut dyn Storage, buyer: Addr, mut good: Goods, price: Coin, buyer_area: &str) -> Result<Order, ContractError> {
+    reserve_goods(storage, &mut good, 1)?;
+    open_order(storage, buyer, good, price, buyer_area, vec![])
+}
+
+// Takes units out of stock. Taking the last one marks the goods as ordered and refunds any
+// other open offers on them.
+fn reserve_goods(storage: &mut dyn Storage, good: &mut Goods, quantity: u32) -> Result<(), ContractError> {
+    if good.stock < quantity {
+        return Err(ContractError::InsufficientStock {});
+    }
+    good.stock -= quantity;
+    good.reserved = good.reserved.saturating_add(quantity);
+    if good.stock == 0 {
+        close_offers(storage, &good.name)?;
+        good.status = Ordered;
+    }
+    GOODS_LIST.save(storage, &good.name, good)?;
+    Ok(())
+}
+
+fn open_order(storage: &mut dyn Storage, buyer: Addr, good: Goods, price: Coin, buyer_area: &str, items: Vec<OrderItem>) -> Result<Order, ContractError> {
+    let order = Order {
+        id: STATE.load(storage)?.order_cnt,
+        buyer,
+        seller: good.clone().seller,
+        goods: good,
+        price,
+        buyer_area: String::from(buyer_area),
+        buyer_location: None,
+        shipper_bids: vec![],
+        shipping_fee: Default::default(),
+        shipper: Addr::unchecked("Dummy_Shipper"),
+        shipper_key: Default::default(),
+        shipper_key_algorithm: Default::default(),
+        buyer_addr_enc: Default::default(),
+        seller_addr_enc: Default::default(),
+        buyer_address: None,
+        seller_address: None,
+        bidding: BiddingMode::Open,
+        auto_select: None,
+        route: vec![],
+        custody_leg: 0,
+        faulty_leg: None,
+        contest_deadline: None,
+        contested: false,
+        delivery_lock: None,
+        proof_deadline: None,
+        reassignment: None,
+        completed: None,
+        items,
+        disputed_items: vec![],
+        status: OrderStatus::Setup
     };
-    let update_order = |d: Option<Order>| -> StdResult<Order> {
+    STATE.update(storage, |mut state| -> Result<_, ContractError> {
+        state.order_cnt += 1;
+        Ok(state)
+    })?;
+    ORDER_LIST.save(storage, &order.id.to_string(), &order)?;
+    Ok(order)
+}
+
+pub fn try_reset(deps: DepsMut, env: Env, info: MessageInfo, name: &str, price: u32) -> Result<Response, ContractError> {
+    let mut good = GOODS_LIST.load(deps.storage, name)?;
+    if good.status != Available {
+        return Err(ContractError::GoodsNotAvailable {});
+    }
+    if good.seller != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if good.listing != Listing::FixedPrice {
+        return Err(ContractError::WrongListingType {});
+    }
+    refund_expired_offers(deps.storage, name, env.block.time)?;
+    good.price.amount = Uint128::from(price);
+    let update_good = |d: Option<Goods>| -> StdResult<Goods> {
         match d {
-            Some(_) => Ok(order.clone()),
+            Some(_) => Ok(good.clone()),
             None => unimplemented!(),
         }
     };
-    ORDER_LIST.update(deps.storage, &id.to_string(), update_order)?;
-    GOODS_LIST.update(deps.storage, &order.goods.name, update_good)?;
+    GOODS_LIST.update(deps.storage, name, update_good)?;
+    Ok(Response::new().add_attribute("method", "try_reset"))
+}
+
+fn assert_batch_size(storage: &dyn Storage, size: usize) -> Result<(), ContractError> {
+    if size == 0 || size > STATE.load(storage)?.max_batch_size as usize {
+        return Err(ContractError::InvalidBatchSize {});
+    }
+    Ok(())
+}
+
+// Any failing item fails the whole batch, so every item_<index> attribute reports a success
+pub fn try_batch_post(mut deps: DepsMut, env: Env, info: MessageInfo, items: Vec<PostItem>) -> Result<Response, ContractError> {
+    assert_batch_size(deps.storage, items.len())?;
+    let mut res = Response::new().add_attribute("method", "try_batch_post");
+    for (i, item) in items.into_iter().enumerate() {
+        let sub = try_post(deps.branch(), env.clone(), info.clone(), &item.name, item.price, &item.denom, &item.seller_area)?;
+        let good = GOODS_LIST.load(deps.storage, &item.name)?;
+        res = forward_response(res.add_attribute(format!("item_{}", i), format!("{}:{}", good.name, good.price)), sub);
+    }
     Ok(res)
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-//        QueryMsg::GetOrderDetail {id} => to_binary(&query_order_detail(deps, id)?),
-//        QueryMsg::GetAddresses {id} => to_binary(&query_addresses(deps, id)?),
-    match msg {
-        QueryMsg::GetGoods {} => to_binary(&query_goods(deps)?),
-        QueryMsg::GetOrders {} => to_binary(&query_orders(deps)?),
-        QueryMsg::GetOrderDetail {id} => to_binary(&query_order_detail(deps, id)?),
-        QueryMsg::GetAddresses {id} => to_binary(&query_address(deps, id)?),
-        QueryMsg::GetBalance {} => to_binary(&query_balance(deps, env)?),
+pub fn try_batch_reset(mut deps: DepsMut, env: Env, info: MessageInfo, items: Vec<ResetItem>) -> Result<Response, ContractError> {
+    assert_batch_size(deps.storage, items.len())?;
+    let mut res = Response::new().add_attribute("method", "try_batch_reset");
+    for (i, item) in items.into_iter().enumerate() {
+        let sub = try_reset(deps.branch(), env.clone(), info.clone(), &item.name, item.price)?;
+        let good = GOODS_LIST.load(deps.storage, &item.name)?;
+        res = forward_response(res.add_attribute(format!("item_{}", i), format!("{}:{}", good.name, good.price)), sub);
+    }
+    Ok(res)
+}
 
+// Each bid is placed with its own share of the funds, so one payment can not back several bids
+pub fn try_batch_take_order(mut deps: DepsMut, info: MessageInfo, items: Vec<TakeOrderItem>) -> Result<Response, ContractError> {
+    assert_batch_size(deps.storage, items.len())?;
+    let mut deposits: Vec<Coin> = vec![];
+    let mut total: Vec<Coin> = vec![];
+    for item in items.iter() {
+        let order = load_order(deps.storage, item.id)?;
+        let deposit = required_collateral(deps.storage, &order)?;
+        match total.iter_mut().find(|c| c.denom == deposit.denom) {
+            Some(c) => c.amount = c.amount.checked_add(deposit.amount).map_err(StdError::from)?,
+            None => total.push(deposit.clone())
+        }
+        deposits.push(deposit);
+    }
+    assert_sent_sufficient_coin(&info.funds, total.clone())?;
+    let mut res = Response::new().add_attribute("method", "try_batch_take_order");
+    for (i, (item, deposit)) in items.into_iter().zip(deposits).enumerate() {
+        let item_info = MessageInfo { sender: info.sender.clone(), funds: vec![deposit.clone()] };
+        let sub = try_take_order(deps.branch(), item_info, item.id, item.pub_key, item.algorithm, item.price)?;
+        res = forward_response(res.add_attribute(format!("item_{}", i), format!("{}:{}", item.id, deposit)), sub);
     }
+    refund_excess(deps.storage, &info.sender, &info.funds, &total)?;
+    Ok(res)
 }
 
-pub fn query_goods(deps: Deps) -> StdResult<GoodsResponse>{
-    // let state = STATE.load(deps.storage)?;
-    let good_list: StdResult<Vec<_>> = GOODS_LIST.range(deps.storage, None, None, Ascending).collect();
-    let good_list = good_list.unwrap();
-    let goods = good_list.iter().map(|x| x.1.clone()).collect();
+// Whatever was sent above the required amounts goes back to the sender
+fn refund_excess(storage: &mut dyn Storage, sender: &Addr, funds: &[Coin], required: &[Coin]) -> StdResult<()> {
+    let excess: Vec<Coin> = funds.iter().map(|c| {
+        let required = required.iter().find(|x| x.denom == c.denom).map(|x| x.amount).unwrap_or_default();
+        coin(c.amount.saturating_sub(required).u128(), c.denom.clone())
+    }).collect();
+    credit_claimable(storage, sender, &excess)
+}
 
-    Ok(GoodsResponse{goods: {goods}})
+// Keeps the attributes, events and messages of a batched handler, each batch item reports its
+// result in an item_{i} attribute, e.g. the listing's price or the bid's deposit
+fn forward_response(res: Response, sub: Response) -> Response {
+    res.add_submessages(sub.messages).add_attributes(sub.attributes).add_events(sub.events)
 }
 
-pub fn query_orders(deps: Deps) -> StdResult<OrdersResponse> {
-    let order_list: StdResult<Vec<_>> = ORDER_LIST.range(deps.storage, None, None, Ascending).collect();
-    let order_list = order_list.unwrap();
-    let orders = order_list.iter().map(|x| x.1.clone()).collect();
+// Shippers bond a stake and declare the areas they serve, re-registering replaces the
+// areas, key and capacity and adds any funds sent to the stake.
+#[allow(clippy::too_many_arguments)]
+pub fn try_register_shipper(deps: DepsMut, info: MessageInfo, service_areas: Vec<String>, pub_key: String, key_algorithm: EncryptionAlgorithm, capacity: u32, rate_per_km: Option<Coin>, max_radius_km: Option<u32>) -> Result<Response, ContractError> {
+    assert_valid_pub_key(&pub_key, key_algorithm)?;
+    let existing = SHIPPERS.may_load(deps.storage, &info.sender)?;
+    let min_shipper_stake = STATE.load(deps.storage)?.min_shipper_stake;
+    let mut stake = match (&existing, &min_shipper_stake) {
+        (Some(shipper), _) if !shipper.stake.amount.is_zero() => shipper.stake.clone(),
+        (_, Some(min_stake)) => coin(0, min_stake.denom.clone()),
+        (_, None) => info.funds.first().map(|c| coin(0, c.denom.clone())).unwrap_or_default()
+    };
+    // the stake is kept in a single denom, anything else would be stranded in the contract
+    for c in info.funds.iter() {
+        if c.denom != stake.denom {
+            return Err(ContractError::DenomNotAccepted {});
+        }
+        stake.amount = stake.amount.checked_add(c.amount).map_err(StdError::from)?;
+    }
+    if let Some(min_stake) = min_shipper_stake {
+        if stake.denom != min_stake.denom || stake.amount < min_stake.amount {
+            return Err(ContractError::InsufficientStake {});
+        }
+    }
+    if let Some(shipper) = &existing {
+        for area in shipper.service_areas.iter() {
+            SHIPPERS_BY_AREA.remove(deps.storage, (area, &info.sender));
+        }
+    }
+    for area in service_areas.iter() {
+        SHIPPERS_BY_AREA.save(deps.storage, (area, &info.sender), &true)?;
+    }
+    let shipper = Shipper {
+        address: info.sender.clone(),
+        service_areas,
+        pub_key,
+        key_algorithm,
+        capacity,
+        active_orders: existing.map(|x| x.active_orders).unwrap_or_default(),
+        stake,
+        rate_per_km,
+        max_radius_km
+    };
+    SHIPPERS.save(deps.storage, &info.sender, &shipper)?;
+    Ok(Response::new().add_attribute("method", "try_register_shipper"))
+}
 
-    Ok(OrdersResponse{orders: {orders}})
+pub fn try_unregister_shipper(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let shipper = SHIPPERS.may_load(deps.storage, &info.sender)?.ok_or(ContractError::ShipperNotRegistered {})?;
+    if shipper.active_orders > 0 {
+        return Err(ContractError::ShipperBusy {});
+    }
+    for area in shipper.service_areas.iter() {
+        SHIPPERS_BY_AREA.remove(deps.storage, (area, &info.sender));
+    }
+    SHIPPERS.remove(deps.storage, &info.sender);
+    credit_claimable(deps.storage, &info.sender, &[shipper.stake])?;
+    Ok(Response::new().add_attribute("method", "try_unregister_shipper"))
 }
 
+fn required_collateral(storage: &dyn Storage, order: &Order) -> StdResult<Coin> {
+    let collateral_bps = STATE.load(storage)?.collateral_bps;
+    Ok(coin(order.price.amount.multiply_ratio(collateral_bps, 10_000u64).u128(), order.price.denom.clone()))
+}
 
-pub fn query_order_detail(deps: Deps, id: u32) -> StdResult<OrderDetailResponse> {
-    let order_list: StdResult<Vec<_>> = ORDER_LIST.range(deps.storage, None, None, Ascending).collect();
-    let order_list = order_list.unwrap();
-    let order = order_list.iter().find(|&x| String::from_utf8(x.clone().0).unwrap() == id.to_string());
-    let order = match order {
-        Some((_, o)) => o.clone(),
-        None => unimplemented!()
-    };
+// Only registered shippers with spare capacity who serve both ends of the order may bid on it.
+fn load_eligible_shipper(storage: &dyn Storage, shipper: &Addr, order: &Order) -> Result<Shipper, ContractError> {
+    let shipper = SHIPPERS.may_load(storage, shipper)?.ok_or(ContractError::ShipperNotRegistered {})?;
+    if let Some(min_stake) = STATE.load(storage)?.min_shipper_stake {
+        if shipper.stake.denom != min_stake.denom || shipper.stake.amount < min_stake.amount {
+            return Err(ContractError::InsufficientStake {});
+        }
+    }
+    if !shipper.service_areas.contains(&order.goods.seller_area) || !shipper.service_areas.contains(&order.buyer_area) {
+        return Err(ContractError::AreaNotServed {});
+    }
+    if shipper.active_orders >= shipper.capacity {
+        return Err(ContractError::ShipperAtCapacity {});
+    }
+    if let (Some(max_radius_km), Some(distance)) = (shipper.max_radius_km, order_distance_m(order)) {
+        if distance > u64::from(max_radius_km) * 1000 {
+            return Err(ContractError::OutOfRange {});
+        }
+    }
+    Ok(shipper)
+}
 
-    Ok(OrderDetailResponse{order: {order}})
+// Distance between the goods and the buyer, known once both sides have set a location.
+fn order_distance_m(order: &Order) -> Option<u64> {
+    match (&order.goods.location, &order.buyer_location) {
+        (Some(from), Some(to)) => Some(distance_m(from, to)),
+        _ => None
+    }
 }
 
-pub fn query_address(deps: Deps, id: u32) -> StdResult<AddressesResponse> {
-    let order_list: StdResult<Vec<_>> = ORDER_LIST.range(deps.storage, None, None, Ascending).collect();
-    let order_list = order_list.unwrap();
-    let order = order_list.iter().find(|&x| String::from_utf8(x.clone().0).unwrap() == id.to_string());
-    let buyer = match order {
-        Some((_, o)) => o.clone().buyer_addr_enc,
-        None => unimplemented!()
+// A bid without its own key uses the registered one, a key sent without algorithm is RSA PKCS#1 v1.5.
+pub fn try_take_order(deps: DepsMut, info: MessageInfo, id: u32, pub_key: Option<String>, algorithm: Option<EncryptionAlgorithm>, price: Coin) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Setup && order.status != Bidding {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if order.bidding != BiddingMode::Open {
+        return Err(ContractError::WrongBiddingMode {});
+    }
+    let shipper = load_eligible_shipper(deps.storage, &info.sender, &order)?;
+    let (pub_key, algorithm) = match pub_key {
+        Some(pub_key) => (pub_key, algorithm.unwrap_or_default()),
+        None => (shipper.pub_key, algorithm.unwrap_or(shipper.key_algorithm))
+    };
+    assert_valid_pub_key(&pub_key, algorithm)?;
+    let deposit = required_collateral(deps.storage, &order)?;
+    assert_sent_sufficient_coin(&info.funds, vec![deposit.clone()])?;
+    refund_excess(deps.storage, &info.sender, &info.funds, std::slice::from_ref(&deposit))?;
+    order.status = Bidding;
+    let bid = ShipperBid {
+        shipper: info.sender,
+        pub_key,
+        algorithm,
+        price,
+        deposit
     };
-    let seller = match order {
-        Some((_, o)) => o.clone().seller_addr_enc,
-        None => unimplemented!()
+    order.shipper_bids.push(bid);
+    let update_order = |d: Option<Order>| -> StdResult<Order> {
+        match d {
+            Some(_) => Ok(order.clone()),
+            None => unimplemented!(),
+        }
     };
+    ORDER_LIST.update(deps.storage, &id.to_string(), update_order)?;
 
-    //Ok(AddressesResponse{buyer: buyer.into_string(), seller: seller.into_string()})
-    Ok(AddressesResponse{buyer: buyer, seller: seller})
+    Ok(Response::new().add_attribute("method", "try_take_order"))
 }
 
-pub fn query_balance(deps: Deps, env: Env) -> StdResult<BalanceResponse> {
-    let balance = deps.querier.query_all_balances(env.contract.address).unwrap();
+pub fn try_seal_bidding(deps: DepsMut, env: Env, info: MessageInfo, id: u32, commit_period: u64, reveal_period: u64, deposit: Coin) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Setup {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if info.sender != order.buyer {
+        return Err(ContractError::Unauthorized {});
+    }
+    if order.bidding != BiddingMode::Open {
+        return Err(ContractError::WrongBiddingMode {});
+    }
+    if commit_period == 0 || reveal_period == 0 {
+        return Err(ContractError::InvalidEndTime {});
+    }
+    let commit_end = env.block.time.plus_seconds(commit_period);
+    order.bidding = BiddingMode::Sealed(SealedBidding {
+        commit_end,
+        reveal_end: commit_end.plus_seconds(reveal_period),
+        deposit,
+        commitments: vec![]
+    });
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new().add_attribute("method", "try_seal_bidding"))
+}
 
-    Ok(BalanceResponse{balance})
+// Nobody committed before the commit period ended, the buyer reopens the order to open bids.
+pub fn try_unseal_bidding(deps: DepsMut, env: Env, info: MessageInfo, id: u32) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Setup {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if info.sender != order.buyer {
+        return Err(ContractError::Unauthorized {});
+    }
+    match &order.bidding {
+        BiddingMode::Sealed(sealed) if env.block.time < sealed.commit_end => return Err(ContractError::BiddingNotEnded {}),
+        BiddingMode::Sealed(_) => {},
+        _ => return Err(ContractError::WrongBiddingMode {})
+    }
+    order.bidding = BiddingMode::Open;
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new().add_attribute("method", "try_unseal_bidding"))
 }
 
-#[cfg(test)]
-mod tests {
-    // use core::panicking::panic;
-    use super::*;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coins, from_binary};
-    use rsa::pkcs8::{FromPublicKey, ToPublicKey};
-    use rsa::RsaPublicKey;
+pub fn try_commit_bid(deps: DepsMut, env: Env, info: MessageInfo, id: u32, commitment: Binary) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Setup && order.status != Bidding {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    let mut sealed = match order.bidding.clone() {
+        BiddingMode::Sealed(sealed) => sealed,
+        _ => return Err(ContractError::WrongBiddingMode {})
+    };
+    if env.block.time >= sealed.commit_end {
+        return Err(ContractError::CommitPeriodEnded {});
+    }
+    load_eligible_shipper(deps.storage, &info.sender, &order)?;
+    if sealed.commitments.iter().any(|x| x.shipper == info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    assert_sent_sufficient_coin(&info.funds, vec![sealed.deposit.clone()])?;
+    sealed.commitments.push(BidCommitment {
+        shipper: info.sender,
+        commitment,
+        revealed: false
+    });
+    order.bidding = BiddingMode::Sealed(sealed);
+    order.status = Bidding;
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new().add_attribute("method", "try_commit_bid"))
+}
 
+#[allow(clippy::too_many_arguments)]
+pub fn try_reveal_bid(deps: DepsMut, env: Env, info: MessageInfo, id: u32, pub_key: String, algorithm: EncryptionAlgorithm, price: Coin, salt: &str) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Bidding {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    let mut sealed = match order.bidding.clone() {
+        BiddingMode::Sealed(sealed) => sealed,
+        _ => return Err(ContractError::WrongBiddingMode {})
+    };
+    if env.block.time < sealed.commit_end || env.block.time >= sealed.reveal_end {
+        return Err(ContractError::NotInRevealPeriod {});
+    }
+    let commitment = match sealed.commitments.iter_mut().find(|x| x.shipper == info.sender && !x.revealed) {
+        Some(x) => x,
+        None => return Err(ContractError::ShipperNotFound {})
+    };
+    if commitment.commitment.as_slice() != bid_commitment(&info.sender, &price, salt, &pub_key, algorithm).as_slice() {
+        return Err(ContractError::CommitmentMismatch {});
+    }
+    assert_valid_pub_key(&pub_key, algorithm)?;
+    // revealing requires the same collateral as an open TakeOrder, the commit deposit is returned
+    let deposit = required_collateral(deps.storage, &order)?;
+    assert_sent_sufficient_coin(&info.funds, vec![deposit.clone()])?;
+    commitment.revealed = true;
+    credit_claimable(deps.storage, &info.sender, &[sealed.deposit.clone()])?;
+    order.shipper_bids.push(ShipperBid {
+        shipper: info.sender,
+        pub_key,
+        algorithm,
+        price,
+        deposit
+    });
+    order.bidding = BiddingMode::Sealed(sealed);
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new().add_attribute("method", "try_reveal_bid"))
+}
 
-    #[test]
-    fn test_post() {
-        let mut deps = mock_dependencies(&[]);
+// Once the reveal period is over, deposits of shippers who never revealed go to the buyer.
+fn close_sealed_bidding(storage: &mut dyn Storage, env: &Env, order: &mut Order) -> Result<(), ContractError> {
+    if let BiddingMode::Sealed(sealed) = &mut order.bidding {
+        if env.block.time < sealed.reveal_end {
+            return Err(ContractError::RevealPeriodNotEnded {});
+        }
+        let forfeited = sealed.commitments.iter().filter(|x| !x.revealed).count() as u128;
+        sealed.commitments.retain(|x| x.revealed);
+        let amount = sealed.deposit.amount.checked_mul(Uint128::from(forfeited)).map_err(StdError::from)?;
+        credit_claimable(storage, &order.buyer, &[coin(amount.u128(), sealed.deposit.denom.clone())])?;
+    }
+    Ok(())
+}
 
-        let msg = InstantiateMsg {};
-        let info = mock_info("creator", &coins(1000, "earth"));
+pub fn try_choose_bid(deps: DepsMut, env: Env, info: MessageInfo, id: u32, shipper: String) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Bidding {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if info.sender != order.buyer {
+        return Err(ContractError::Unauthorized {});
+    }
+    if order.auto_select.is_some() {
+        return Err(ContractError::WrongBiddingMode {});
+    }
+    close_sealed_bidding(deps.storage, &env, &mut order)?;
+    match order.shipper_bids.iter().find(|x| x.shipper == Addr::unchecked(shipper.clone())).cloned() {
+        Some(x) => {
+            assert_sent_sufficient_coin(&info.funds, coins(x.price.clone().amount.checked_mul(Uint128::from(2u32)).unwrap().u128(), x.price.clone().denom))?;
+            assign_route(deps.storage, &mut order, vec![x])?;
+            ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
 
-        // we can just call .unwrap() to assert this was a success
-        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-        assert_eq!(0, res.messages.len());
+            Ok(Response::new().add_attribute("method", "try_choose_bid"))
+        }
+        None => {
+            Err(ContractError::ShipperNotFound {})
+        }
+    }
+}
 
-        let msg = ExecuteMsg::Post {
-            name: String::from("TV"),
-            price: 200,
-            denom: String::from("LUNA"),
-            seller_area: String::from("Montreal")
-        };
-        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-//        // it worked, let's query the state
-//        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount {}).unwrap();
-//        let value: CountResponse = from_binary(&res).unwrap();
-//        assert_eq!(17, value.count);
+pub fn try_auto_select(deps: DepsMut, env: Env, info: MessageInfo, id: u32, bidding_period: u64, policy: SelectionPolicy, max_fee: Coin) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Setup && order.status != Bidding {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if info.sender != order.buyer {
+        return Err(ContractError::Unauthorized {});
+    }
+    if order.auto_select.is_some() {
+        return Err(ContractError::WrongBiddingMode {});
+    }
+    assert_sent_sufficient_coin(&info.funds, coins(max_fee.amount.checked_mul(Uint128::from(2u32)).map_err(StdError::from)?.u128(), max_fee.denom.clone()))?;
+    order.auto_select = Some(AutoSelect {
+        bidding_end: env.block.time.plus_seconds(bidding_period),
+        policy,
+        max_fee
+    });
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new().add_attribute("method", "try_auto_select"))
+}
+
+// Anyone can trigger the selection once the bidding window of an auto-select order has closed.
+pub fn try_select_bid(deps: DepsMut, env: Env, id: u32) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Bidding {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    let auto_select = match order.auto_select.clone() {
+        Some(auto_select) => auto_select,
+        None => return Err(ContractError::WrongBiddingMode {})
+    };
+    if env.block.time < auto_select.bidding_end {
+        return Err(ContractError::BiddingNotEnded {});
+    }
+    close_sealed_bidding(deps.storage, &env, &mut order)?;
+    let bid = best_bid(deps.storage, &env, &order, &auto_select)?.ok_or(ContractError::ShipperNotFound {})?;
+    // the buyer pre-paid twice the maximum fee, the part above twice the chosen fee goes back
+    let surplus = auto_select.max_fee.amount.checked_sub(bid.price.amount).map_err(StdError::from)?
+        .checked_mul(Uint128::from(2u32)).map_err(StdError::from)?;
+    credit_claimable(deps.storage, &order.buyer, &coins(surplus.u128(), auto_select.max_fee.denom))?;
+    assign_route(deps.storage, &mut order, vec![bid.clone()])?;
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_select_bid")
+        .add_attribute("shipper", bid.shipper))
+}
+
+// Buyers get their escrow back when the bidding window closed without any bid SelectBid could pick.
+// The order then goes back to manual selection.
+pub fn try_cancel_auto_select(deps: DepsMut, env: Env, info: MessageInfo, id: u32) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Setup && order.status != Bidding {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if info.sender != order.buyer {
+        return Err(ContractError::Unauthorized {});
+    }
+    let auto_select = match order.auto_select.clone() {
+        Some(auto_select) => auto_select,
+        None => return Err(ContractError::WrongBiddingMode {})
+    };
+    if env.block.time < auto_select.bidding_end {
+        return Err(ContractError::BiddingNotEnded {});
+    }
+    close_sealed_bidding(deps.storage, &env, &mut order)?;
+    if best_bid(deps.storage, &env, &order, &auto_select)?.is_some() {
+        return Err(ContractError::BidsAvailable {});
+    }
+    let escrow = auto_select.max_fee.amount.checked_mul(Uint128::from(2u32)).map_err(StdError::from)?;
+    credit_claimable(deps.storage, &order.buyer, &coins(escrow.u128(), auto_select.max_fee.denom))?;
+    order.auto_select = None;
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new().add_attribute("method", "try_cancel_auto_select"))
+}
+
+// The highest scoring bid within the maximum fee from a shipper that can still take the order
+fn best_bid(storage: &dyn Storage, env: &Env, order: &Order, auto_select: &AutoSelect) -> Result<Option<ShipperBid>, ContractError> {
+    let mut best: Option<(ShipperBid, u64)> = None;
+    for bid in order.shipper_bids.iter() {
+        if bid.price.denom != auto_select.max_fee.denom || bid.price.amount > auto_select.max_fee.amount {
+            continue;
+        }
+        if load_eligible_shipper(storage, &bid.shipper, order).is_err() {
+            continue;
+        }
+        let score = bid_score(storage, env, auto_select, bid)?;
+        // ties go to the cheaper bid, then to the earlier one
+        let better = match &best {
+            Some((b, s)) => score > *s || (score == *s && bid.price.amount < b.price.amount),
+            None => true
+        };
+        if better {
+            best = Some((bid.clone(), score));
+        }
+    }
+    Ok(best.map(|(bid, _)| bid))
+}
+
+// Higher is better. Price scores run from 0 (bid at max_fee) to 100 (free shipping),
+// reputation is the shipper's overall reputation score.
+fn bid_score(storage: &dyn Storage, env: &Env, auto_select: &AutoSelect, bid: &ShipperBid) -> StdResult<u64> {
+    let price_score = if auto_select.max_fee.amount.is_zero() {
+        100
+    } else {
+        (auto_select.max_fee.amount - bid.price.amount).multiply_ratio(100u128, auto_select.max_fee.amount).u128() as u64
+    };
+    Ok(match auto_select.policy {
+        SelectionPolicy::LowestPrice => price_score,
+        SelectionPolicy::BestReputation => shipper_reputation(storage, env, &bid.shipper)?,
+        SelectionPolicy::Weighted { price_weight, reputation_weight } => {
+            let reputation = shipper_reputation(storage, env, &bid.shipper)?;
+            price_weight.saturating_mul(price_score).saturating_add(reputation_weight.saturating_mul(reputation))
+        }
+    })
+}
+
+fn shipper_reputation(storage: &dyn Storage, env: &Env, shipper: &Addr) -> StdResult<u64> {
+    Ok(reputation::score(&reputation::load(storage, shipper, env.block.time)?).score)
+}
+
+// Frees one unit of capacity once an order assigned to the shipper is settled.
+fn release_shipper(storage: &mut dyn Storage, shipper: &Addr) -> StdResult<()> {
+    if let Some(mut x) = SHIPPERS.may_load(storage, shipper)? {
+        x.active_orders = x.active_orders.saturating_sub(1);
+        SHIPPERS.save(storage, shipper, &x)?;
+    }
+    Ok(())
+}
+
+// Moves the order to WaitingAddressUpload with the given bids as the legs of its route,
+// the losing shippers get their collateral back.
+fn assign_route(storage: &mut dyn Storage, order: &mut Order, bids: Vec<ShipperBid>) -> Result<(), ContractError> {
+    for x in order.shipper_bids.iter().filter(|x| !bids.iter().any(|b| b.shipper == x.shipper)) {
+        credit_claimable(storage, &x.shipper, &[bid_deposit(order, x)])?;
+    }
+    let mut total_fee = coin(0, bids[0].price.denom.clone());
+    order.route = vec![];
+    for bid in bids.iter() {
+        if bid.price.denom != total_fee.denom {
+            return Err(ContractError::InvalidRoute {});
+        }
+        total_fee.amount = total_fee.amount.checked_add(bid.price.amount).map_err(StdError::from)?;
+        if !is_legacy_bid(bid) {
+            let mut shipper = load_eligible_shipper(storage, &bid.shipper, order)?;
+            shipper.active_orders += 1;
+            SHIPPERS.save(storage, &bid.shipper, &shipper)?;
+        }
+        order.route.push(ShippingLeg {
+            shipper: bid.shipper.clone(),
+            pub_key: bid.pub_key.clone(),
+            algorithm: bid.algorithm,
+            fee: bid.price.clone(),
+            deposit: bid_deposit(order, bid),
+            handed_off: false,
+            received: false
+        });
+    }
+    order.route[0].received = true;
+    order.custody_leg = 0;
+    order.status = WaitingAddressUpload;
+    order.shipper = bids[0].shipper.clone();
+    order.shipper_key = bids[0].pub_key.clone();
+    order.shipper_key_algorithm = bids[0].algorithm;
+    order.shipping_fee = total_fee;
+    Ok(())
+}
+
+// Bids stored before the shipper registry carry no deposit, their shippers are not registered
+// and put up the order price as collateral.
+fn is_legacy_bid(bid: &ShipperBid) -> bool {
+    bid.deposit.denom.is_empty()
+}
+
+fn bid_deposit(order: &Order, bid: &ShipperBid) -> Coin {
+    if is_legacy_bid(bid) { order.price.clone() } else { bid.deposit.clone() }
+}
+
+// The buyer picks several bids, in delivery order, to relay the package. The seller encrypts
+// their address for the first leg's key and the buyer for the last leg's key.
+pub fn try_choose_route(deps: DepsMut, env: Env, info: MessageInfo, id: u32, shippers: Vec<String>) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Bidding {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if info.sender != order.buyer {
+        return Err(ContractError::Unauthorized {});
+    }
+    if order.auto_select.is_some() {
+        return Err(ContractError::WrongBiddingMode {});
+    }
+    close_sealed_bidding(deps.storage, &env, &mut order)?;
+    if shippers.is_empty() {
+        return Err(ContractError::InvalidRoute {});
+    }
+    let mut bids: Vec<ShipperBid> = vec![];
+    for shipper in shippers.iter() {
+        if bids.iter().any(|x| x.shipper == *shipper) {
+            return Err(ContractError::InvalidRoute {});
+        }
+        match order.shipper_bids.iter().find(|x| x.shipper == *shipper) {
+            Some(x) => bids.push(x.clone()),
+            None => return Err(ContractError::ShipperNotFound {})
+        }
+    }
+    assign_route(deps.storage, &mut order, bids)?;
+    assert_sent_sufficient_coin(&info.funds, coins(order.shipping_fee.amount.checked_mul(Uint128::from(2u32)).map_err(StdError::from)?.u128(), order.shipping_fee.denom.clone()))?;
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new().add_attribute("method", "try_choose_route"))
+}
+
+// Custody passes to the next leg once both the outgoing and the incoming shipper have confirmed.
+pub fn try_confirm_handoff(deps: DepsMut, info: MessageInfo, id: u32) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Shipping {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    let current = order.custody_leg as usize;
+    if current + 1 >= order.route.len() {
+        return Err(ContractError::InvalidRoute {});
+    }
+    if order.route[current].shipper == info.sender {
+        order.route[current].handed_off = true;
+    } else if order.route[current + 1].shipper == info.sender {
+        order.route[current + 1].received = true;
+    } else {
+        return Err(ContractError::Unauthorized {});
+    }
+    if order.route[current].handed_off && order.route[current + 1].received {
+        order.custody_leg += 1;
+        order.shipper = order.route[current + 1].shipper.clone();
+    }
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new().add_attribute("method", "try_confirm_handoff"))
+}
+
+// The incoming shipper refuses a damaged package, which blames the leg currently in custody.
+pub fn try_report_handoff_damage(deps: DepsMut, env: Env, info: MessageInfo, id: u32) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Shipping {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    let current = order.custody_leg as usize;
+    if current + 1 >= order.route.len() {
+        return Err(ContractError::InvalidRoute {});
+    }
+    if order.route[current + 1].shipper != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    order.faulty_leg = Some(order.custody_leg);
+    order.contest_deadline = Some(env.block.time.plus_seconds(CONTEST_PERIOD));
+    order.status = DisputingBroken;
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new().add_attribute("method", "try_report_handoff_damage"))
+}
+
+// The leg blamed for broken goods objects to the claim before the contest period ends,
+// its collateral can then only be slashed by the arbiter.
+pub fn try_contest_damage(deps: DepsMut, env: Env, info: MessageInfo, id: u32) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != DisputingBroken || order.contested {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    match order.route.get(blamed_leg(&order)) {
+        Some(leg) if leg.shipper == info.sender => {},
+        _ => return Err(ContractError::Unauthorized {})
+    }
+    if order.contest_deadline.is_none_or(|deadline| env.block.time >= deadline) {
+        return Err(ContractError::ContestPeriodEnded {});
+    }
+    order.contested = true;
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new().add_attribute("method", "try_contest_damage"))
+}
+
+// The arbiter settles a contested claim. An upheld claim is settled like a confirmed dispute,
+// a rejected one puts the order back on its way with the blamed leg still in custody.
+pub fn try_resolve_damage(deps: DepsMut, env: Env, info: MessageInfo, id: u32, upheld: bool) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != DisputingBroken || !order.contested {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if info.sender != STATE.load(deps.storage)?.arbiter {
+        return Err(ContractError::Unauthorized {});
+    }
+    if upheld {
+        settle_dispute(deps.storage, &env, order)?;
+    } else {
+        order.status = Shipping;
+        order.faulty_leg = None;
+        order.contest_deadline = None;
+        order.contested = false;
+        order.disputed_items = vec![];
+        ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    }
+    Ok(Response::new().add_attribute("method", "try_resolve_damage"))
+}
+
+// The leg held responsible for broken goods, the one in custody unless a handoff was refused
+fn blamed_leg(order: &Order) -> usize {
+    order.faulty_leg.unwrap_or(order.custody_leg) as usize
+}
+
+// Raw ciphertext uploads predate the envelope and are only accepted for RSA PKCS#1 v1.5 keys
+pub fn try_upload_address(deps: DepsMut, env: Env, info: MessageInfo, id: u32, address_enc: Vec<u8>) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != WaitingAddressUpload {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if order.shipper_key_algorithm != EncryptionAlgorithm::RsaPkcs1v15 {
+        return Err(ContractError::InvalidEnvelope {});
+    }
+    if order.buyer == info.sender {
+        order.buyer_addr_enc = address_enc;
+    }
+    else if order.seller == info.sender {
+        order.seller_addr_enc = address_enc;
+    }
+    else {
+        return Err(ContractError::Unauthorized {});
+    }
+    if has_addresses(&order) {
+        start_shipping(&env, &mut order);
+    }
+    let update_order = |d: Option<Order>| -> StdResult<Order> {
+        match d {
+            Some(_) => Ok(order.clone()),
+            None => unimplemented!(),
+        }
+    };
+    ORDER_LIST.update(deps.storage, &id.to_string(), update_order)?;
+    Ok(Response::new().add_attribute("method", "try_upload_address"))
+}
+
+pub fn try_upload_address_envelope(deps: DepsMut, env: Env, info: MessageInfo, id: u32, envelope: AddressEnvelope) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != WaitingAddressUpload {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    assert_valid_envelope(&envelope, order.shipper_key_algorithm)?;
+    if order.buyer == info.sender {
+        order.buyer_addr_enc = vec![];
+        order.buyer_address = Some(envelope);
+    }
+    else if order.seller == info.sender {
+        order.seller_addr_enc = vec![];
+        order.seller_address = Some(envelope);
+    }
+    else {
+        return Err(ContractError::Unauthorized {});
+    }
+    if has_addresses(&order) {
+        start_shipping(&env, &mut order);
+    }
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new().add_attribute("method", "try_upload_address_envelope"))
+}
+
+// With a delivery lock the shipper gets DELIVERY_PROOF_PERIOD to prove delivery before the buyer may dispute.
+fn start_shipping(env: &Env, order: &mut Order) {
+    order.status = Shipping;
+    if order.delivery_lock.is_some() {
+        order.proof_deadline = Some(env.block.time.plus_seconds(DELIVERY_PROOF_PERIOD));
+    }
+}
+
+fn assert_proof_period_over(env: &Env, order: &Order) -> Result<(), ContractError> {
+    match order.proof_deadline {
+        Some(deadline) if env.block.time < deadline => Err(ContractError::DeliveryLocked {}),
+        _ => Ok(())
+    }
+}
+
+fn has_addresses(order: &Order) -> bool {
+    (!order.buyer_addr_enc.is_empty() || order.buyer_address.is_some()) && (!order.seller_addr_enc.is_empty() || order.seller_address.is_some())
+}
+
+// Legacy raw uploads are reported as version 0 envelopes
+fn address_envelope(address_enc: &[u8], envelope: &Option<AddressEnvelope>) -> Option<AddressEnvelope> {
+    match envelope {
+        Some(envelope) => Some(envelope.clone()),
+        None if !address_enc.is_empty() => Some(AddressEnvelope {
+            algorithm: EncryptionAlgorithm::RsaPkcs1v15,
+            version: 0,
+            nonce: None,
+            ciphertext: Binary::from(address_enc)
+        }),
+        None => None
+    }
+}
+
+pub fn try_confirm(deps: DepsMut, env: Env, info: MessageInfo, id: u32) -> Result<Response, ContractError> {
+    let order = load_order(deps.storage, id)?;
+    if order.status != Shipping {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if order.buyer != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    settle_delivery(deps.storage, &env, order)?;
+    Ok(Response::new().add_attribute("method", "try_confirm"))
+}
+
+// Buyer and seller both name the replacement for the shipper in custody, e.g. after it lost its key.
+// Naming a different shipper starts a new proposal.
+pub fn try_reassign_shipper(deps: DepsMut, info: MessageInfo, id: u32, shipper: String) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != WaitingAddressUpload && order.status != Shipping {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if info.sender != order.buyer && info.sender != order.seller {
+        return Err(ContractError::Unauthorized {});
+    }
+    let shipper = deps.api.addr_validate(&shipper)?;
+    if order.route.iter().any(|x| x.shipper == shipper) {
+        return Err(ContractError::InvalidRoute {});
+    }
+    let mut proposal = match order.reassignment.take() {
+        Some(x) if x.shipper == shipper => x,
+        _ => ReassignmentProposal { shipper, approvals: vec![] }
+    };
+    if !proposal.approvals.contains(&info.sender) {
+        proposal.approvals.push(info.sender);
+    }
+    order.reassignment = Some(proposal);
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new().add_attribute("method", "try_reassign_shipper"))
+}
+
+// The approved shipper takes over the leg in custody with its registered key and the same fee, the
+// addresses have to be uploaded again for the new key. The outgoing shipper gets its collateral back
+// if it drops out before pickup, once it holds the package its collateral goes to the insurance pool.
+pub fn try_accept_reassignment(deps: DepsMut, env: Env, info: MessageInfo, id: u32) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != WaitingAddressUpload && order.status != Shipping {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    let proposal = match order.reassignment.take() {
+        Some(x) if x.shipper == info.sender => x,
+        _ => return Err(ContractError::Unauthorized {})
+    };
+    if !proposal.approvals.contains(&order.buyer) || !proposal.approvals.contains(&order.seller) {
+        return Err(ContractError::ReassignmentNotApproved {});
+    }
+    let mut shipper = load_eligible_shipper(deps.storage, &info.sender, &order)?;
+    let deposit = required_collateral(deps.storage, &order)?;
+    assert_sent_sufficient_coin(&info.funds, vec![deposit.clone()])?;
+    let picked_up = order.status == Shipping;
+    let leg = order.route.get_mut(order.custody_leg as usize).ok_or(ContractError::InvalidRoute {})?;
+    shipper.active_orders += 1;
+    SHIPPERS.save(deps.storage, &info.sender, &shipper)?;
+
+    if picked_up {
+        credit_insurance_pool(deps.storage, &leg.deposit)?;
+    } else {
+        credit_claimable(deps.storage, &leg.shipper, std::slice::from_ref(&leg.deposit))?;
+    }
+    release_shipper(deps.storage, &leg.shipper)?;
+    let record = Reassignment {
+        leg: order.custody_leg,
+        from: leg.shipper.clone(),
+        to: info.sender.clone(),
+        time: env.block.time
+    };
+    leg.shipper = info.sender.clone();
+    leg.pub_key = shipper.pub_key.clone();
+    leg.algorithm = shipper.key_algorithm;
+    leg.deposit = deposit;
+
+    order.shipper = info.sender;
+    order.shipper_key = shipper.pub_key;
+    order.shipper_key_algorithm = shipper.key_algorithm;
+    order.buyer_addr_enc = vec![];
+    order.seller_addr_enc = vec![];
+    order.buyer_address = None;
+    order.seller_address = None;
+    order.status = WaitingAddressUpload;
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    REASSIGNMENTS.update(deps.storage, &id.to_string(), |history| -> StdResult<Vec<Reassignment>> {
+        let mut history = history.unwrap_or_default();
+        history.push(record);
+        Ok(history)
+    })?;
+    Ok(Response::new().add_attribute("method", "try_accept_reassignment"))
+}
+
+pub fn try_register_key(deps: DepsMut, env: Env, info: MessageInfo, pub_key: String, algorithm: EncryptionAlgorithm) -> Result<Response, ContractError> {
+    assert_valid_pub_key(&pub_key, algorithm)?;
+    let key = UserKey {
+        pub_key,
+        algorithm,
+        registered: env.block.time,
+        revoked: None
+    };
+    USER_KEYS.save(deps.storage, &info.sender, &key)?;
+    KEY_HISTORY.update(deps.storage, &info.sender, |history| -> StdResult<Vec<UserKey>> {
+        let mut history = history.unwrap_or_default();
+        if let Some(previous) = history.last_mut() {
+            previous.revoked.get_or_insert(env.block.time);
+        }
+        history.push(key);
+        Ok(history)
+    })?;
+    Ok(Response::new().add_attribute("method", "try_register_key"))
+}
+
+pub fn try_revoke_key(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    if USER_KEYS.may_load(deps.storage, &info.sender)?.is_none() {
+        return Err(ContractError::KeyNotFound {});
+    }
+    USER_KEYS.remove(deps.storage, &info.sender);
+    KEY_HISTORY.update(deps.storage, &info.sender, |history| -> StdResult<Vec<UserKey>> {
+        let mut history = history.unwrap_or_default();
+        if let Some(current) = history.last_mut() {
+            current.revoked = Some(env.block.time);
+        }
+        Ok(history)
+    })?;
+    Ok(Response::new().add_attribute("method", "try_revoke_key"))
+}
+
+// Participants append to the order's message log. Every copy must be encrypted for the recipient's
+// current key, shippers use the key of their leg and buyers and sellers their registered key.
+pub fn try_send_message(deps: DepsMut, env: Env, info: MessageInfo, id: u32, recipients: Vec<MessageRecipient>) -> Result<Response, ContractError> {
+    let order = load_order(deps.storage, id)?;
+    if !is_participant(&order, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if recipients.is_empty() {
+        return Err(ContractError::InvalidRecipient {});
+    }
+    let mut entries: Vec<MessageEntry> = vec![];
+    for x in recipients {
+        let recipient = deps.api.addr_validate(&x.address)?;
+        if !is_participant(&order, &recipient) || entries.iter().any(|e| e.recipient == recipient) {
+            return Err(ContractError::InvalidRecipient {});
+        }
+        let algorithm = match order.route.iter().find(|leg| leg.shipper == recipient) {
+            Some(leg) => leg.algorithm,
+            None => USER_KEYS.may_load(deps.storage, &recipient)?.ok_or(ContractError::InvalidRecipient {})?.algorithm
+        };
+        assert_valid_envelope(&x.envelope, algorithm)?;
+        if x.envelope.ciphertext.len() > MAX_MESSAGE_BYTES {
+            return Err(ContractError::MessageTooLarge {});
+        }
+        entries.push(MessageEntry { recipient, envelope: x.envelope });
+    }
+    let mut messages = MESSAGES.may_load(deps.storage, &id.to_string())?.unwrap_or_default();
+    if messages.len() >= MAX_MESSAGES {
+        return Err(ContractError::TooManyMessages {});
+    }
+    messages.push(Message {
+        sender: info.sender,
+        time: env.block.time,
+        entries
+    });
+    MESSAGES.save(deps.storage, &id.to_string(), &messages)?;
+    Ok(Response::new().add_attribute("method", "try_send_message"))
+}
+
+// The shipper currently holding the package records its progress, the note is encrypted for the buyer and seller.
+pub fn try_post_checkpoint(deps: DepsMut, env: Env, info: MessageInfo, id: u32, kind: CheckpointKind, note_enc: Option<Binary>) -> Result<Response, ContractError> {
+    let order = load_order(deps.storage, id)?;
+    if order.status != Shipping {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if order.shipper != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut checkpoints = TRACKING.may_load(deps.storage, &id.to_string())?.unwrap_or_default();
+    if checkpoints.len() >= MAX_CHECKPOINTS {
+        return Err(ContractError::TooManyCheckpoints {});
+    }
+    checkpoints.push(Checkpoint {
+        kind,
+        shipper: info.sender,
+        time: env.block.time,
+        note_enc
+    });
+    TRACKING.save(deps.storage, &id.to_string(), &checkpoints)?;
+    Ok(Response::new().add_attribute("method", "try_post_checkpoint"))
+}
+
+// Hash-lock delivery: the buyer commits to a secret before shipping starts and only hands it over
+// together with the package.
+// The lock is a sha256 hash
+fn assert_valid_delivery_lock(delivery_lock: &Option<Binary>) -> Result<(), ContractError> {
+    match delivery_lock {
+        Some(hash) if hash.len() != 32 => Err(ContractError::InvalidPreimage {}),
+        _ => Ok(())
+    }
+}
+
+pub fn try_set_delivery_lock(deps: DepsMut, info: MessageInfo, id: u32, hash: Binary) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Setup && order.status != Bidding && order.status != WaitingAddressUpload {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if order.buyer != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let delivery_lock = Some(hash);
+    assert_valid_delivery_lock(&delivery_lock)?;
+    order.delivery_lock = delivery_lock;
+    ORDER_LIST.save(deps.storage, &id.to_string(), &order)?;
+    Ok(Response::new().add_attribute("method", "try_set_delivery_lock"))
+}
+
+// The shipper holding the package settles the order with the buyer's secret, exactly as Confirm would.
+pub fn try_prove_delivery(deps: DepsMut, env: Env, info: MessageInfo, id: u32, preimage: Binary) -> Result<Response, ContractError> {
+    let order = load_order(deps.storage, id)?;
+    if order.status != Shipping {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if order.shipper != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let lock = order.delivery_lock.clone().ok_or(ContractError::NoDeliveryLock {})?;
+    if sha256(preimage.as_slice()) != lock.as_slice() {
+        return Err(ContractError::InvalidPreimage {});
+    }
+    settle_delivery(deps.storage, &env, order)?;
+    Ok(Response::new().add_attribute("method", "try_prove_delivery"))
+}
+
+// Marks the order confirmed and the goods sold, then pays the seller and every leg of the route
+// its fee and returns the leg's deposit, nothing can be slashed once the order is delivered.
+fn settle_delivery(storage: &mut dyn Storage, env: &Env, mut order: Order) -> Result<(), ContractError> {
+    order.status = Confirmed;
+    order.completed = Some(env.block.time);
+    for (name, quantity) in order_goods(&order) {
+        close_goods(storage, &name, quantity, Sold, 0)?;
+    }
+    order.goods = GOODS_LIST.load(storage, &order.goods.name)?;
+    ORDER_LIST.save(storage, &order.id.to_string(), &order)?;
+    let payout = seller_payout(storage, &order)?;
+    let subsidy = shipping_subsidy(storage, &order)?;
+    credit_claimable(storage, &payout, &coins((order.price.amount - subsidy).u128(), order.price.denom.clone()))?;
+    credit_claimable(storage, &order.buyer, &coins(subsidy.u128(), order.price.denom.clone()))?;
+    reputation::record(storage, &order.seller, env.block.time, ReputationEvent::Completed(order.price.clone()))?;
+    for leg in order.route.iter() {
+        credit_claimable(storage, &leg.shipper, &[leg.fee.clone(), leg.deposit.clone()])?;
+        reputation::record(storage, &leg.shipper, env.block.time, ReputationEvent::Completed(leg.fee.clone()))?;
+        release_shipper(storage, &leg.shipper)?;
+    }
+    Ok(())
+}
+
+// Orders stored before relay routes keep their only shipper in order.shipper, once a shipper is
+// assigned they are given the equivalent one-leg route. The leg's deposit is the order price,
+// which is what TakeOrder took as collateral back then.
+fn load_order(storage: &dyn Storage, id: u32) -> StdResult<Order> {
+    let mut order = ORDER_LIST.load(storage, &id.to_string())?;
+    if order.route.is_empty() && !matches!(order.status, Setup | Bidding | Confirmed | Disputed) {
+        order.route = vec![ShippingLeg {
+            shipper: order.shipper.clone(),
+            pub_key: order.shipper_key.clone(),
+            algorithm: order.shipper_key_algorithm,
+            fee: order.shipping_fee.clone(),
+            deposit: order.price.clone(),
+            handed_off: false,
+            received: true
+        }];
+        order.custody_leg = 0;
+    }
+    Ok(order)
+}
+
+// Names and quantities of the goods an order covers
+fn order_goods(order: &Order) -> Vec<(String, u32)> {
+    if order.items.is_empty() {
+        return vec![(order.goods.name.clone(), 1)];
+    }
+    order.items.iter().map(|x| (x.name.clone(), x.quantity)).collect()
+}
+
+// Releases the units a settled order held and puts those the seller took back into stock. Goods that
+// still have units in stock stay available, sold out fixed-price goods are listed again once units come back.
+fn close_goods(storage: &mut dyn Storage, name: &str, quantity: u32, status: GoodsStatus, restock: u32) -> StdResult<()> {
+    let mut good = GOODS_LIST.load(storage, name)?;
+    good.reserved = good.reserved.saturating_sub(quantity);
+    good.stock = good.stock.saturating_add(restock);
+    if good.status == Ordered {
+        good.status = if restock > 0 && good.listing == Listing::FixedPrice { Available } else { status };
+    }
+    GOODS_LIST.save(storage, name, &good)
+}
+
+// Checks the items a buyer disputes against the cart, an empty list disputes the whole order
+fn dispute_items(order: &Order, items: Vec<CartItem>) -> Result<Vec<OrderItem>, ContractError> {
+    let mut disputed: Vec<OrderItem> = vec![];
+    for item in items {
+        let ordered = order.items.iter().find(|x| x.name == item.name).ok_or(ContractError::InvalidCart {})?;
+        if item.quantity == 0 || item.quantity > ordered.quantity || disputed.iter().any(|x| x.name == item.name) {
+            return Err(ContractError::InvalidCart {});
+        }
+        disputed.push(OrderItem { name: item.name, quantity: item.quantity, unit_price: ordered.unit_price.clone() });
+    }
+    Ok(disputed)
+}
+
+// Value of the disputed items, or of the whole order when it is disputed as a whole
+fn disputed_value(order: &Order) -> StdResult<Coin> {
+    if order.disputed_items.is_empty() {
+        return Ok(order.price.clone());
+    }
+    let mut amount = Uint128::zero();
+    for item in order.disputed_items.iter() {
+        amount = amount.checked_add(item.unit_price.amount.checked_mul(Uint128::from(item.quantity))?)?;
+    }
+    Ok(coin(amount.u128(), order.price.denom.clone()))
+}
+
+pub fn try_dispute_broken(deps: DepsMut, env: Env, info: MessageInfo, id: u32, items: Vec<CartItem>) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Shipping {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if order.buyer != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    assert_proof_period_over(&env, &order)?;
+    order.disputed_items = dispute_items(&order, items)?;
+    order.contest_deadline = Some(env.block.time.plus_seconds(CONTEST_PERIOD));
+    order.status = DisputingBroken;
+    let update_order = |d: Option<Order>| -> StdResult<Order> {
+        match d {
+            Some(_) => Ok(order.clone()),
+            None => unimplemented!(),
+        }
+    };
+    ORDER_LIST.update(deps.storage, &id.to_string(), update_order)?;
+    Ok(Response::new().add_attribute("method", "try_dispute_broken"))
+}
+
+pub fn try_dispute_unsatisfied(deps: DepsMut, env: Env, info: MessageInfo, id: u32, items: Vec<CartItem>) -> Result<Response, ContractError> {
+    let mut order = load_order(deps.storage, id)?;
+    if order.status != Shipping {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if order.buyer != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    assert_proof_period_over(&env, &order)?;
+    order.disputed_items = dispute_items(&order, items)?;
+    order.status = DisputingUnsatisfied;
+    let update_order = |d: Option<Order>| -> StdResult<Order> {
+        match d {
+            Some(_) => Ok(order.clone()),
+            None => unimplemented!(),
+        }
+    };
+    ORDER_LIST.update(deps.storage, &id.to_string(), update_order)?;
+    Ok(Response::new().add_attribute("method", "try_dispute_unsatisfied"))
+}
+
+// Broken-goods claims wait for the contest period, contested ones are left to the arbiter.
+pub fn try_dispute_confirm(deps: DepsMut, env: Env, info: MessageInfo, id: u32) -> Result<Response, ContractError> {
+    let order = load_order(deps.storage, id)?;
+    if order.status != DisputingBroken && order.status != DisputingUnsatisfied {
+        return Err(ContractError::OrderNotAvailable {});
+    }
+    if order.seller != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if order.status == DisputingBroken {
+        if order.contested {
+            return Err(ContractError::DisputeContested {});
+        }
+        if order.contest_deadline.is_some_and(|deadline| env.block.time < deadline) {
+            return Err(ContractError::ContestPeriodNotEnded {});
+        }
+    }
+    settle_dispute(deps.storage, &env, order)?;
+    Ok(Response::new().add_attribute("method", "try_dispute_confirm"))
+}
+
+fn settle_dispute(storage: &mut dyn Storage, env: &Env, mut order: Order) -> Result<(), ContractError> {
+    let disputed = disputed_value(&order)?;
+    // unsatisfied buyers are refunded and send the goods back, broken goods are paid for by the shipper
+    let restock = order.status == DisputingUnsatisfied;
+    match order.status {
+        DisputingBroken => {
+            // the seller is paid and legs that handed the package on are paid their fee, the buyer gets back
+            // the rest of the shipping deposit. Only the leg that held custody when the damage happened is
+            // penalized, its collateral is slashed to reimburse the buyer for the damaged goods.
+            let faulty_leg = blamed_leg(&order);
+            let faulty = order.route.get(faulty_leg).cloned().ok_or(ContractError::InvalidRoute {})?;
+            let mut earned = Uint128::zero();
+            for leg in order.route[..faulty_leg].iter() {
+                credit_claimable(storage, &leg.shipper, &[leg.fee.clone(), leg.deposit.clone()])?;
+                reputation::record(storage, &leg.shipper, env.block.time, ReputationEvent::Completed(leg.fee.clone()))?;
+                earned = earned.checked_add(leg.fee.amount).map_err(StdError::from)?;
+            }
+            for leg in order.route[faulty_leg + 1..].iter() {
+                credit_claimable(storage, &leg.shipper, std::slice::from_ref(&leg.deposit))?;
+            }
+            let refund = order.shipping_fee.amount.checked_mul(Uint128::from(2u32)).map_err(StdError::from)?.checked_sub(earned).map_err(StdError::from)?;
+            credit_claimable(storage, &order.buyer, &coins(refund.u128(), order.shipping_fee.denom.clone()))?;
+            let payout = seller_payout(storage, &order)?;
+            credit_claimable(storage, &payout, &[order.clone().price])?;
+            let reimbursement = slash_collateral(storage, &faulty.deposit, &disputed)?;
+            credit_claimable(storage, &order.buyer, &[reimbursement])?;
+            order.faulty_leg = Some(faulty_leg as u32);
+            reputation::record(storage, &faulty.shipper, env.block.time, ReputationEvent::DisputeLost)?;
+            reputation::record(storage, &order.seller, env.block.time, ReputationEvent::Completed(order.price.clone()))?;
+        },
+        DisputingUnsatisfied => {
+            for leg in order.route.iter() {
+                let fee = coin(leg.fee.amount.checked_mul(Uint128::from(2u32)).unwrap().u128(), leg.fee.denom.clone());
+                credit_claimable(storage, &leg.shipper, &[fee, leg.deposit.clone()])?;
+                reputation::record(storage, &leg.shipper, env.block.time, ReputationEvent::Completed(leg.fee.clone()))?;
+            }
+            // the buyer is refunded the disputed items, the seller keeps the price of the rest
+            let kept = order.price.amount - disputed.amount;
+            credit_claimable(storage, &order.buyer, std::slice::from_ref(&disputed))?;
+            let payout = seller_payout(storage, &order)?;
+            credit_claimable(storage, &payout, &coins(kept.u128(), order.price.denom.clone()))?;
+            reputation::record(storage, &order.seller, env.block.time, ReputationEvent::DisputeLost)?;
+        },
+        _ => unimplemented!()
+    };
+    reputation::record(storage, &order.buyer, env.block.time, ReputationEvent::DisputeWon)?;
+    for leg in order.route.iter() {
+        release_shipper(storage, &leg.shipper)?;
+    }
+    order.status = Disputed;
+    order.completed = Some(env.block.time);
+    for (name, quantity) in order_goods(&order) {
+        let returned = if order.disputed_items.is_empty() {
+            quantity
+        } else {
+            order.disputed_items.iter().find(|x| x.name == name).map(|x| x.quantity).unwrap_or_default()
+        };
+        close_goods(storage, &name, quantity, if returned > 0 { Returned } else { Sold }, if restock { returned } else { 0 })?;
+    }
+    order.goods = GOODS_LIST.load(storage, &order.goods.name)?;
+    ORDER_LIST.save(storage, &order.id.to_string(), &order)?;
+    Ok(())
+}
+
+// Splits slashed collateral between the insurance pool and the buyer's loss. Anything above the loss
+// goes to the pool as well, a shortfall is covered by the pool as far as its balance allows.
+fn slash_collateral(storage: &mut dyn Storage, collateral: &Coin, loss: &Coin) -> Result<Coin, ContractError> {
+    let insurance_bps = STATE.load(storage)?.insurance_bps;
+    let mut pool = INSURANCE_POOL.may_load(storage, &loss.denom)?.unwrap_or_default();
+    let mut reimbursed = Uint128::zero();
+    if collateral.denom == loss.denom {
+        let cut = collateral.amount.multiply_ratio(insurance_bps, 10_000u64);
+        let available = collateral.amount - cut;
+        reimbursed = available.min(loss.amount);
+        pool += cut + (available - reimbursed);
+    } else {
+        credit_insurance_pool(storage, collateral)?;
+    }
+    let top_up = (loss.amount - reimbursed).min(pool);
+    pool -= top_up;
+    INSURANCE_POOL.save(storage, &loss.denom, &pool)?;
+    Ok(coin((reimbursed + top_up).u128(), loss.denom.clone()))
+}
+
+fn credit_insurance_pool(storage: &mut dyn Storage, amount: &Coin) -> StdResult<()> {
+    INSURANCE_POOL.update(storage, &amount.denom, |balance| -> StdResult<Uint128> {
+        Ok(balance.unwrap_or_default().checked_add(amount.amount)?)
+    })?;
+    Ok(())
+}
+
+// Participants of a confirmed or disputed order rate each other once per role pair
+// during REVIEW_PERIOD after completion.
+pub fn try_review(deps: DepsMut, env: Env, info: MessageInfo, id: u32, subject: String, rating: u8, review_hash: Binary) -> Result<Response, ContractError> {
+    let order = load_order(deps.storage, id)?;
+    let completed = match (&order.status, order.completed) {
+        (Confirmed, Some(time)) | (Disputed, Some(time)) => time,
+        _ => return Err(ContractError::OrderNotAvailable {})
+    };
+    if env.block.time > completed.plus_seconds(REVIEW_PERIOD) {
+        return Err(ContractError::ReviewPeriodEnded {});
+    }
+    let subject = deps.api.addr_validate(&subject)?;
+    let reviewer_role = participant_role(&order, &info.sender).ok_or(ContractError::Unauthorized {})?;
+    let subject_role = participant_role(&order, &subject).ok_or(ContractError::InvalidReview {})?;
+    if reviewer_role == subject_role || !(1..=5).contains(&rating) || review_hash.len() != 32 {
+        return Err(ContractError::InvalidReview {});
+    }
+    let order_key = id.to_string();
+    let key = (&subject, order_key.as_str(), &info.sender);
+    if REVIEWS.may_load(deps.storage, key)?.is_some() {
+        return Err(ContractError::AlreadyReviewed {});
+    }
+    let review = Review {
+        order_id: id,
+        reviewer: info.sender.clone(),
+        reviewer_role,
+        subject: subject.clone(),
+        subject_role,
+        rating,
+        review_hash,
+        time: env.block.time
+    };
+    REVIEWS.save(deps.storage, key, &review)?;
+    reputation::record(deps.storage, &subject, env.block.time, ReputationEvent::Rated(rating))?;
+    Ok(Response::new().add_attribute("method", "try_review"))
+}
+
+fn participant_role(order: &Order, address: &Addr) -> Option<Role> {
+    if *address == order.buyer {
+        Some(Role::Buyer)
+    } else if *address == order.seller {
+        Some(Role::Seller)
+    } else if order.route.iter().any(|x| x.shipper == *address) {
+        Some(Role::Shipper)
+    } else {
+        None
+    }
+}
+
+pub fn try_withdraw(deps: DepsMut, info: MessageInfo, denoms: Vec<String>) -> Result<Response, ContractError> {
+    // an empty list withdraws every denom the sender can claim
+    let denoms = if denoms.is_empty() {
+        let claimable: StdResult<Vec<_>> = CLAIMABLE.prefix(&info.sender).range(deps.storage, None, None, Ascending).collect();
+        claimable?.into_iter().map(|(denom, _)| String::from_utf8(denom).unwrap()).collect()
+    } else {
+        denoms
+    };
+    let mut amount = vec![];
+    for denom in denoms.iter() {
+        if let Some(balance) = CLAIMABLE.may_load(deps.storage, (&info.sender, denom))? {
+            CLAIMABLE.remove(deps.storage, (&info.sender, denom));
+            amount.push(coin(balance.u128(), denom));
+        }
+    }
+    if amount.is_empty() {
+        return Err(ContractError::NothingToWithdraw {});
+    }
+    Ok(Response::new()
+        .add_attribute("method", "try_withdraw")
+        .add_message(CosmosMsg::Bank(BankMsg::Send { to_address: info.sender.into_string(), amount })))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+//        QueryMsg::GetOrderDetail {id} => to_binary(&query_order_detail(deps, id)?),
+//        QueryMsg::GetAddresses {id} => to_binary(&query_addresses(deps, id)?),
+    match msg {
+        QueryMsg::GetGoods {} => to_binary(&query_goods(deps)?),
+        QueryMsg::GetPrice {name} => to_binary(&query_price(deps, env, name)?),
+        QueryMsg::GetOffers {name} => to_binary(&query_offers(deps, env, name)?),
+        QueryMsg::GetStore {owner} => to_binary(&query_store(deps, owner)?),
+        QueryMsg::Categories {} => to_binary(&query_categories(deps)?),
+        QueryMsg::GoodsByCategory {category, start_after, limit} => to_binary(&query_goods_by_category(deps, category, start_after, limit)?),
+        QueryMsg::GoodsByTag {tag, start_after, limit} => to_binary(&query_goods_by_tag(deps, tag, start_after, limit)?),
+        QueryMsg::StoreListings {owner, start_after, limit} => to_binary(&query_store_listings(deps, owner, start_after, limit)?),
+        QueryMsg::GetOrders {} => to_binary(&query_orders(deps)?),
+        QueryMsg::GetOrderDetail {id, viewer} => to_binary(&query_order_detail(deps, env, id, viewer)?),
+        QueryMsg::GetAddresses {id, viewer} => to_binary(&query_address(deps, env, id, viewer)?),
+        QueryMsg::Tracking {id, viewer} => to_binary(&query_tracking(deps, env, id, viewer)?),
+        QueryMsg::Reassignments {id} => to_binary(&query_reassignments(deps, id)?),
+        QueryMsg::Messages {id, viewer, start_after, limit} => to_binary(&query_messages(deps, env, id, viewer, start_after, limit)?),
+        QueryMsg::GetBalance {} => to_binary(&query_balance(deps, env)?),
+        QueryMsg::Claimable {address} => to_binary(&query_claimable(deps, address)?),
+        QueryMsg::InsurancePool {} => to_binary(&query_insurance_pool(deps)?),
+        QueryMsg::GetReviews {address} => to_binary(&query_reviews(deps, address)?),
+        QueryMsg::GetReputation {address} => to_binary(&query_reputation(deps, env, address)?),
+        QueryMsg::GetKey {address} => to_binary(&query_key(deps, address)?),
+        QueryMsg::KeyHistory {address} => to_binary(&query_key_history(deps, address)?),
+        QueryMsg::GetShipper {address} => to_binary(&query_shipper(deps, address)?),
+        QueryMsg::GetShippers {area} => to_binary(&query_shippers(deps, area)?),
+        QueryMsg::GetShippingEstimates {id} => to_binary(&query_shipping_estimates(deps, id)?),
+
+    }
+}
+
+pub fn query_goods(deps: Deps) -> StdResult<GoodsResponse>{
+    // let state = STATE.load(deps.storage)?;
+    let good_list: StdResult<Vec<_>> = GOODS_LIST.range(deps.storage, None, None, Ascending).collect();
+    let good_list = good_list.unwrap();
+    let goods = good_list.iter().map(|x| x.1.clone()).collect();
+
+    Ok(GoodsResponse{goods: {goods}})
+}
+
+pub fn query_store(deps: Deps, owner: String) -> StdResult<StoreResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let store = STORES.load(deps.storage, &owner)?;
+
+    Ok(StoreResponse{store})
+}
+
+pub fn query_store_listings(deps: Deps, owner: String, start_after: Option<String>, limit: Option<u32>) -> StdResult<GoodsResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize;
+    let start = start_after.map(|x| Bound::exclusive(x.as_bytes()));
+    let names: StdResult<Vec<_>> = STORE_GOODS.prefix(&owner).range(deps.storage, start, None, Ascending).take(limit).collect();
+
+    Ok(GoodsResponse{goods: load_goods(deps.storage, names?)?})
+}
+
+pub fn query_categories(deps: Deps) -> StdResult<CategoriesResponse> {
+    let categories: StdResult<Vec<_>> = CATEGORIES.keys(deps.storage, None, None, Ascending)
+        .map(|x| String::from_utf8(x).map_err(StdError::from))
+        .collect();
+
+    Ok(CategoriesResponse{categories: categories?})
+}
+
+pub fn query_goods_by_category(deps: Deps, category: String, start_after: Option<String>, limit: Option<u32>) -> StdResult<GoodsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize;
+    let start = start_after.map(|x| Bound::exclusive(x.as_bytes()));
+    let names: StdResult<Vec<_>> = GOODS_BY_CATEGORY.prefix(&category).range(deps.storage, start, None, Ascending).take(limit).collect();
+
+    Ok(GoodsResponse{goods: load_goods(deps.storage, names?)?})
+}
+
+pub fn query_goods_by_tag(deps: Deps, tag: String, start_after: Option<String>, limit: Option<u32>) -> StdResult<GoodsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize;
+    let start = start_after.map(|x| Bound::exclusive(x.as_bytes()));
+    let names: StdResult<Vec<_>> = GOODS_BY_TAG.prefix(&tag.to_lowercase()).range(deps.storage, start, None, Ascending).take(limit).collect();
+
+    Ok(GoodsResponse{goods: load_goods(deps.storage, names?)?})
+}
+
+// Loads the goods behind the names of a secondary index page
+fn load_goods(storage: &dyn Storage, names: Vec<(Vec<u8>, bool)>) -> StdResult<Vec<Goods>> {
+    names.into_iter()
+        .map(|(name, _)| GOODS_LIST.load(storage, &String::from_utf8(name).map_err(StdError::from)?))
+        .collect()
+}
+
+pub fn query_price(deps: Deps, env: Env, name: String) -> StdResult<PriceResponse> {
+    let good = GOODS_LIST.load(deps.storage, &name)?;
+    let price = match good.listing {
+        Listing::FixedPrice => good.price,
+        Listing::EnglishAuction(auction) => match auction.highest_bid {
+            Some(highest) => highest.amount,
+            None => good.price
+        },
+        Listing::DutchAuction(auction) => coin(dutch_auction_price(&auction, env.block.time).u128(), good.price.denom)
+    };
+
+    Ok(PriceResponse{price})
+}
+
+pub fn query_offers(deps: Deps, env: Env, name: String) -> StdResult<OffersResponse> {
+    let offer_list: StdResult<Vec<_>> = OFFER_LIST.prefix(&name).range(deps.storage, None, None, Ascending).collect();
+    let offers = offer_list?.into_iter()
+        .map(|x| x.1)
+        .filter(|o| o.expires > env.block.time)
+        .collect();
+
+    Ok(OffersResponse{offers})
+}
+
+pub fn query_orders(deps: Deps) -> StdResult<OrdersResponse> {
+    let order_list: StdResult<Vec<_>> = ORDER_LIST.range(deps.storage, None, None, Ascending).collect();
+    let order_list = order_list.unwrap();
+    let orders = order_list.iter().map(|x| redact_addresses(x.1.clone())).collect();
+
+    Ok(OrdersResponse{orders: {orders}})
+}
+
+
+pub fn query_order_detail(deps: Deps, env: Env, id: u32, viewer: Option<Viewer>) -> StdResult<OrderDetailResponse> {
+    let order = load_order(deps.storage, id)?;
+    let order = if can_view_addresses(deps, &env, &order, viewer)? { order } else { redact_addresses(order) };
+
+    Ok(OrderDetailResponse{order: {order}})
+}
+
+pub fn query_address(deps: Deps, env: Env, id: u32, viewer: Option<Viewer>) -> StdResult<AddressesResponse> {
+    let order = query_order_detail(deps, env, id, viewer)?.order;
+    let buyer_envelope = address_envelope(&order.buyer_addr_enc, &order.buyer_address);
+    let seller_envelope = address_envelope(&order.seller_addr_enc, &order.seller_address);
+
+    Ok(AddressesResponse{buyer: order.buyer_addr_enc, seller: order.seller_addr_enc, buyer_envelope, seller_envelope})
+}
+
+// Buyer, seller, the shippers on the route and the arbiter may read the encrypted addresses,
+// provided the viewer proves its address with the registered viewing key.
+fn can_view_addresses(deps: Deps, env: &Env, order: &Order, viewer: Option<Viewer>) -> StdResult<bool> {
+    let address = match authenticate_viewer(deps, env, viewer)? {
+        Some(address) => address,
+        None => return Ok(false)
+    };
+    let arbiter = STATE.load(deps.storage)?.arbiter;
+    Ok(is_participant(order, &address) || address == arbiter)
+}
+
+// The permit has to be signed by the key the address registered and is bound to this contract.
+// This only decides what queries return: contract state can be read raw, the addresses themselves
+// stay confidential because they are encrypted for the shipper key.
+fn authenticate_viewer(deps: Deps, env: &Env, viewer: Option<Viewer>) -> StdResult<Option<Addr>> {
+    let viewer = match viewer {
+        Some(viewer) => viewer,
+        None => return Ok(None)
+    };
+    let address = deps.api.addr_validate(&viewer.address)?;
+    let pub_key = match PERMIT_KEYS.may_load(deps.storage, &address)? {
+        Some(pub_key) => pub_key,
+        None => return Ok(None)
+    };
+    let digest = sha256(&permit_message(env.contract.address.as_str(), address.as_str()));
+    match deps.api.secp256k1_verify(&digest, viewer.signature.as_slice(), pub_key.as_slice()) {
+        Ok(true) => Ok(Some(address)),
+        _ => Ok(None)
+    }
+}
+
+fn is_participant(order: &Order, address: &Addr) -> bool {
+    *address == order.buyer || *address == order.seller || order.route.iter().any(|x| x.shipper == *address)
+}
+
+fn redact_addresses(mut order: Order) -> Order {
+    order.buyer_addr_enc = vec![];
+    order.seller_addr_enc = vec![];
+    order.buyer_address = None;
+    order.seller_address = None;
+    order
+}
+
+pub fn query_tracking(deps: Deps, env: Env, id: u32, viewer: Viewer) -> StdResult<TrackingResponse> {
+    let order = load_order(deps.storage, id)?;
+    match authenticate_viewer(deps, &env, Some(viewer))? {
+        Some(address) if is_participant(&order, &address) => {},
+        _ => return Err(StdError::generic_err("Unauthorized"))
+    };
+    let checkpoints = TRACKING.may_load(deps.storage, &id.to_string())?.unwrap_or_default();
+
+    Ok(TrackingResponse{checkpoints})
+}
+
+pub fn query_reassignments(deps: Deps, id: u32) -> StdResult<ReassignmentsResponse> {
+    let pending = load_order(deps.storage, id)?.reassignment;
+    let history = REASSIGNMENTS.may_load(deps.storage, &id.to_string())?.unwrap_or_default();
+
+    Ok(ReassignmentsResponse{pending, history})
+}
+
+pub fn query_messages(deps: Deps, env: Env, id: u32, viewer: Viewer, start_after: Option<u32>, limit: Option<u32>) -> StdResult<MessagesResponse> {
+    let order = load_order(deps.storage, id)?;
+    let address = match authenticate_viewer(deps, &env, Some(viewer))? {
+        Some(address) if is_participant(&order, &address) => address,
+        _ => return Err(StdError::generic_err("Unauthorized"))
+    };
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize;
+    let start = start_after.map(|x| x as usize + 1).unwrap_or_default();
+    let messages = MESSAGES.may_load(deps.storage, &id.to_string())?.unwrap_or_default()
+        .into_iter()
+        .enumerate()
+        .skip(start)
+        .filter_map(|(index, message)| {
+            let envelope = message.entries.iter().find(|x| x.recipient == address).map(|x| x.envelope.clone());
+            if envelope.is_none() && message.sender != address {
+                return None;
+            }
+            Some(MessageView { index: index as u32, sender: message.sender, time: message.time, envelope })
+        })
+        .take(limit)
+        .collect();
+
+    Ok(MessagesResponse{messages})
+}
+
+pub fn query_balance(deps: Deps, env: Env) -> StdResult<BalanceResponse> {
+    let balance = deps.querier.query_all_balances(env.contract.address).unwrap();
+
+    Ok(BalanceResponse{balance})
+}
+
+pub fn query_claimable(deps: Deps, address: String) -> StdResult<ClaimableResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let claimable: StdResult<Vec<_>> = CLAIMABLE.prefix(&address).range(deps.storage, None, None, Ascending).collect();
+    let claimable = claimable?.into_iter().map(|(denom, amount)| coin(amount.u128(), String::from_utf8(denom).unwrap())).collect();
+
+    Ok(ClaimableResponse{claimable})
+}
+
+pub fn query_insurance_pool(deps: Deps) -> StdResult<InsurancePoolResponse> {
+    let pool: StdResult<Vec<_>> = INSURANCE_POOL.range(deps.storage, None, None, Ascending).collect();
+    let balance = pool?.into_iter().filter(|(_, amount)| !amount.is_zero()).map(|(denom, amount)| coin(amount.u128(), String::from_utf8(denom).unwrap())).collect();
+
+    Ok(InsurancePoolResponse{balance})
+}
+
+pub fn query_reviews(deps: Deps, address: String) -> StdResult<ReviewsResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let reviews: StdResult<Vec<_>> = REVIEWS.sub_prefix(&address).range(deps.storage, None, None, Ascending).collect();
+    let reviews = reviews?.into_iter().map(|(_, review)| review).collect();
+
+    Ok(ReviewsResponse{reviews})
+}
+
+pub fn query_reputation(deps: Deps, env: Env, address: String) -> StdResult<ReputationResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let reputation = reputation::load(deps.storage, &address, env.block.time)?;
+    let score = reputation::score(&reputation);
+
+    Ok(ReputationResponse{reputation, score})
+}
+
+pub fn query_key(deps: Deps, address: String) -> StdResult<KeyResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let key = USER_KEYS.may_load(deps.storage, &address)?;
+
+    Ok(KeyResponse{key})
+}
+
+pub fn query_key_history(deps: Deps, address: String) -> StdResult<KeyHistoryResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let keys = KEY_HISTORY.may_load(deps.storage, &address)?.unwrap_or_default();
+
+    Ok(KeyHistoryResponse{keys})
+}
+
+pub fn query_shipper(deps: Deps, address: String) -> StdResult<ShipperResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let shipper = SHIPPERS.load(deps.storage, &address)?;
+
+    Ok(ShipperResponse{shipper})
+}
+
+pub fn query_shippers(deps: Deps, area: String) -> StdResult<ShippersResponse> {
+    let shipper_list: StdResult<Vec<_>> = SHIPPERS_BY_AREA.prefix(&area).range(deps.storage, None, None, Ascending).collect();
+    let shippers: StdResult<Vec<_>> = shipper_list?.into_iter()
+        .map(|(address, _)| SHIPPERS.load(deps.storage, &Addr::unchecked(String::from_utf8(address).unwrap())))
+        .collect();
+
+    Ok(ShippersResponse{shippers: shippers?})
+}
+
+// Fees quoted by every registered shipper who could take the order at their per-km rate.
+pub fn query_shipping_estimates(deps: Deps, id: u32) -> StdResult<ShippingEstimatesResponse> {
+    let order = load_order(deps.storage, id)?;
+    let distance = order_distance_m(&order).ok_or_else(|| StdError::generic_err("order locations are not set"))?;
+    let km = distance.div_ceil(1000);
+    let shipper_list: StdResult<Vec<_>> = SHIPPERS_BY_AREA.prefix(&order.goods.seller_area).range(deps.storage, None, None, Ascending).collect();
+    let mut estimates = vec![];
+    for (address, _) in shipper_list? {
+        let address = Addr::unchecked(String::from_utf8(address).unwrap());
+        if load_eligible_shipper(deps.storage, &address, &order).is_err() {
+            continue;
+        }
+        if let Some(rate) = SHIPPERS.load(deps.storage, &address)?.rate_per_km {
+            estimates.push(ShippingEstimate {
+                shipper: address,
+                fee: Coin {amount: rate.amount.checked_mul(Uint128::from(km))?, denom: rate.denom}
+            });
+        }
+    }
+
+    Ok(ShippingEstimatesResponse{distance_m: distance, estimates})
+}
+
+#[cfg(test)]
+mod tests {
+    // use core::panicking::panic;
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{coins, from_binary};
+    use crate::reputation::ReputationScore;
+    use crate::client::crypto::{decrypt_address, encrypt_address, encrypt_address_legacy, generate_permit_key, generate_rsa_key, sign_permit};
+
+    const SHIPPER_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAwKv+JfJzQQJItKEB2VsQ
+f+nBcqFgdKwuocygAhGW74WDCgiJrwJDiPNxgCwf4XuBrniJi7SJb0M1oicBf9WG
+TsAf915TXcCw2VKrjUI5uDLjEHi4cc/vIe7afZjyBT5DslYQqkodSv4NQTZR8SJ/
+5RcdEUsFbLhIVNtJgAqpPXmZ6PUad6zzmBKS6rscFju22AiVfeuozD6nUT3mOpTN
+ApdxtmQUbE0U5m6ujUGUOuEzS83Z61UrAu9UgLRqBoVI4jKILCTteTxm1fp+fEkx
+CFLRHiN4CB+tr1N0aFC+sSqAj2H+CpB8w+z8plWakE9m8QceEtCzi31XhTT02ia2
+bwIDAQAB
+-----END PUBLIC KEY-----
+";
+    const SHIPPER_KEY_1: &str = "-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAxVlcrHBElMB1k6BMMaHN
+EC2E89dYCp2t6w4kIuK+3R/SQtiBjWZ5i/xpeh1EXNiJfvvtBaGE9EVdds0oX7R3
+dEKZFIIwSNGo/Fk71qyWE9cqspf7LnlPxIAnTO2wW13wwNnbbEMD1qKq1S8DtkB1
+gn+3EGD1oZicFoZh88+E5DyAeRYmaK43NySCKh4/10VGzcFUcPw7PKzHtuNoawW0
+6NpUMa9c/Hxgvu8itskuG22QVXb/NTu70XAX4G7ZLBFFYBPuQfydxknNgZCbtzlh
+8La+Ndu9Fho98J6AGdrwQXlnwobuGlG34jktpYGHdZtRq2lr2nCVgV55QGPibGse
+VQIDAQAB
+-----END PUBLIC KEY-----
+";
+    const SHIPPER_KEY_2: &str = "-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAxh1UTxwerNMFZqp5x1E0
+/wdcnYJElXyilKhdPkReDwygA7gxk241OpgYrr3wD7my4aa5vgOnBGl1axkGZ9z9
+y4WnGC3FAMh+iHs/xW6nGXIVNKYRLaBpGmNabN/L50V+2O8D7qXK7GsaZmxVyTIt
+DWCH7AXpmHj3KybJE8oOdQSSkUsNNTmb9CPebAQEulUi3WLvZ1riEye8AWXYRyIY
+PfSkV+vPgwks7Gw0G1MAP+Kc1OWTuIQLsbcjzbRkpnOAnNldBNltYYP0LdBzqw0g
+XriKQTMXg9EV60AJp3bHwlHCRn0bqVvM41fz7X5/kppTfTZUd1XBFTmVGA7h4uF+
+jwIDAQAB
+-----END PUBLIC KEY-----
+";
+
+    fn viewer(deps: DepsMut, address: &str) -> Option<Viewer> {
+        let (signing_key, pub_key) = generate_permit_key();
+        let _res = execute(deps, mock_env(), mock_info(address, &[]), ExecuteMsg::SetPermitKey {pub_key}).unwrap();
+        Some(Viewer {address: String::from(address), signature: sign_permit(&signing_key, mock_env().contract.address.as_str(), address)})
+    }
+
+    fn register_shipper(deps: DepsMut, shipper: &str) {
+        let msg = ExecuteMsg::RegisterShipper {
+            service_areas: vec![String::from("Montreal"), String::from("Ottawa")],
+            pub_key: String::from(SHIPPER_KEY),
+            key_algorithm: None,
+            capacity: 10,
+            rate_per_km: None,
+            max_radius_km: None
+        };
+        let _res = execute(deps, mock_env(), mock_info(shipper, &coins(100, "LUNA")), msg).unwrap();
+    }
+
+    #[test]
+    fn test_post() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("creator", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+//        // it worked, let's query the state
+//        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount {}).unwrap();
+//        let value: CountResponse = from_binary(&res).unwrap();
+//        assert_eq!(17, value.count);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetGoods {}).unwrap();
+        let value: GoodsResponse = from_binary(&res).unwrap();
+        println!("{:?}", value);
+    }
+
+    #[test]
+    fn test_legacy_state() {
+        let mut deps = mock_dependencies(&[]);
+
+        // records as the first release stored them, before any of the later fields existed
+        deps.storage.set(STATE.as_slice(), br#"{"order_cnt":4,"owner":"owner"}"#);
+        for id in 0..4 {
+            let goods = format!(r#"{{"name":"TV{0}","seller":"seller","price":{{"denom":"LUNA","amount":"200"}},"seller_area":"Montreal","status":"Ordered"}}"#, id);
+            let order = format!(r#"{{"id":{0},"buyer":"buyer{0}","seller":"seller","goods":{1},"price":{{"denom":"LUNA","amount":"200"}},"buyer_area":"Montreal","shipper_bids":[{{"shipper":"shipper{0}","pub_key":"rsa1","price":{{"denom":"LUNA","amount":"10"}}}}],"shipping_fee":{{"denom":"","amount":"0"}},"shipper":"Dummy_Shipper","shipper_key":"","buyer_addr_enc":[],"seller_addr_enc":[],"status":"Bidding"}}"#, id, goods);
+            deps.storage.set(&GOODS_LIST.key(&format!("TV{}", id)), goods.as_bytes());
+            deps.storage.set(&ORDER_LIST.key(&id.to_string()), order.as_bytes());
+        }
+
+        let state = STATE.load(&deps.storage).unwrap();
+        assert_eq!((10_000, 1_000, 50), (state.collateral_bps, state.insurance_bps, state.max_batch_size));
+        assert_eq!(Addr::unchecked(""), state.arbiter);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(Listing::FixedPrice, value.order.goods.listing);
+        assert_eq!(BiddingMode::Open, value.order.bidding);
+        assert!(value.order.route.is_empty() && value.order.delivery_lock.is_none());
+
+        let msg = ExecuteMsg::UpdateConfig {
+            min_shipper_stake: None,
+            collateral_bps: None,
+            insurance_bps: None,
+            arbiter: Some(String::from("arbiter")),
+            max_batch_size: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        assert_eq!(Addr::unchecked("arbiter"), STATE.load(&deps.storage).unwrap().arbiter);
+
+        // the legacy shippers never registered, their bids still win and they hold the order price as collateral
+        for id in 0..4u32 {
+            let buyer = format!("buyer{}", id);
+            let msg = ExecuteMsg::ChooseBid {id, shipper: format!("shipper{}", id)};
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(&buyer, &coins(20, "LUNA")), msg).unwrap();
+            let order = ORDER_LIST.load(&deps.storage, &id.to_string()).unwrap();
+            assert_eq!(coin(200, "LUNA"), order.route[0].deposit);
+            if id == 3 {
+                continue;
+            }
+            for sender in [buyer.as_str(), "seller"] {
+                let msg = ExecuteMsg::UploadAddress {id, address_enc: String::from("my address").into_bytes()};
+                let _res = execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg).unwrap();
+            }
+        }
+
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer0", &[]), ExecuteMsg::Confirm {id: 0}).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer1", &[]), ExecuteMsg::DisputeBroken {id: 1, items: vec![]}).unwrap();
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(CONTEST_PERIOD);
+        let _res = execute(deps.as_mut(), env, mock_info("seller", &[]), ExecuteMsg::DisputeConfirm {id: 1}).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer2", &[]), ExecuteMsg::DisputeUnsatisfied {id: 2, items: vec![]}).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), ExecuteMsg::DisputeConfirm {id: 2}).unwrap();
+
+        // the legacy shipper of order 3 hands over to a registered courier before pickup
+        register_shipper(deps.as_mut(), "courier");
+        for sender in ["buyer3", "seller"] {
+            let msg = ExecuteMsg::ReassignShipper {id: 3, shipper: String::from("courier")};
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg).unwrap();
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("courier", &coins(200, "LUNA")), ExecuteMsg::AcceptReassignment {id: 3}).unwrap();
+        let order = ORDER_LIST.load(&deps.storage, "3").unwrap();
+        assert_eq!(Addr::unchecked("courier"), order.route[0].shipper);
+
+        for (address, claimable) in [
+            ("shipper0", vec![coin(210, "LUNA")]),
+            ("buyer1", vec![coin(220, "LUNA")]),
+            ("shipper1", vec![]),
+            ("buyer2", vec![coin(200, "LUNA")]),
+            ("shipper2", vec![coin(220, "LUNA")]),
+            ("shipper3", vec![coin(200, "LUNA")]),
+            ("seller", vec![coin(400, "LUNA")])
+        ] {
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from(address)}).unwrap();
+            let value: ClaimableResponse = from_binary(&res).unwrap();
+            assert_eq!(claimable, value.claimable);
+        }
+    }
+
+    #[test]
+    fn test_store() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::SetStore {
+            name: String::from("TV Shop"),
+            description_hash: Binary::from(sha256(b"TVs and more")),
+            default_area: String::from("Montreal"),
+            return_policy: ReturnPolicy::ReturnWithin {days: 14},
+            payout_address: Some(String::from("treasury")),
+            accepted_denoms: vec![String::from("LUNA")],
+            shipping_subsidy: Some(coin(4, "LUNA"))
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStore {owner: String::from("seller")}).unwrap();
+        let value: StoreResponse = from_binary(&res).unwrap();
+        assert_eq!(Addr::unchecked("treasury"), value.store.payout_address);
+
+        // listings without a seller area get the store's default area
+        for name in ["TV1", "TV2", "TV3"] {
+            let msg = ExecuteMsg::Post {
+                name: String::from(name),
+                price: 200,
+                denom: String::from("LUNA"),
+                seller_area: String::new()
+            };
+            let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        }
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV4"),
+            price: 200,
+            denom: String::from("ATOM"),
+            seller_area: String::new()
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        match res {
+            Err(ContractError::DenomNotAccepted {}) => {},
+            _ => panic!("Store only accepts LUNA!")
+        }
+        // goods of other sellers do not show up in the store
+        let msg = ExecuteMsg::Post {
+            name: String::from("Radio"),
+            price: 50,
+            denom: String::from("ATOM"),
+            seller_area: String::from("Toronto")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("seller2", &[]), msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::StoreListings {owner: String::from("seller"), start_after: None, limit: Some(2)}).unwrap();
+        let value: GoodsResponse = from_binary(&res).unwrap();
+        assert_eq!(vec!["TV1", "TV2"], value.goods.iter().map(|x| x.name.as_str()).collect::<Vec<_>>());
+        assert_eq!(String::from("Montreal"), value.goods[0].seller_area);
+        assert_eq!(Some(Addr::unchecked("seller")), value.goods[0].store);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::StoreListings {owner: String::from("seller"), start_after: Some(String::from("TV2")), limit: None}).unwrap();
+        let value: GoodsResponse = from_binary(&res).unwrap();
+        assert_eq!(vec!["TV3"], value.goods.iter().map(|x| x.name.as_str()).collect::<Vec<_>>());
+
+        // the sale is paid out to the store's payout address, less the shipping the store covers
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(200, "LUNA")), ExecuteMsg::Buy {name: String::from("TV1"), buyer_area: String::from("Montreal"), delivery_lock: None}).unwrap();
+        register_shipper(deps.as_mut(), "shipper");
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &coins(200, "LUNA")), ExecuteMsg::TakeOrder {id: 0, pub_key: None, algorithm: None, price: coin(10, "LUNA")}).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), ExecuteMsg::ChooseBid {id: 0, shipper: String::from("shipper")}).unwrap();
+        for address in ["buyer", "seller"] {
+            let msg = ExecuteMsg::UploadAddress {id: 0, address_enc: String::from("my address").into_bytes()};
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(address, &[]), msg).unwrap();
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), ExecuteMsg::Confirm {id: 0}).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("treasury")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(196, "LUNA")], value.claimable);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("buyer")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(4, "LUNA")], value.claimable);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("seller")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert!(value.claimable.is_empty());
+    }
+
+    #[test]
+    fn test_goods_metadata() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let _res = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        for category in ["electronics", "furniture"] {
+            let _res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::AddCategory {category: String::from(category)}).unwrap();
+        }
+        let res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), ExecuteMsg::AddCategory {category: String::from("toys")});
+        match res {
+            Err(ContractError::Unauthorized {}) => {},
+            _ => panic!("Only the owner manages categories!")
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Categories {}).unwrap();
+        let value: CategoriesResponse = from_binary(&res).unwrap();
+        assert_eq!(vec!["electronics", "furniture"], value.categories);
+
+        for name in ["TV", "Radio", "Sofa"] {
+            let msg = ExecuteMsg::Post {
+                name: String::from(name),
+                price: 200,
+                denom: String::from("LUNA"),
+                seller_area: String::from("Montreal")
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), msg).unwrap();
+        }
+        for (name, category, tags) in [("TV", "electronics", vec!["Used", "4K"]), ("Radio", "electronics", vec!["used"]), ("Sofa", "furniture", vec!["new"])] {
+            let msg = ExecuteMsg::SetGoodsMetadata {
+                name: String::from(name),
+                category: Some(String::from(category)),
+                tags: tags.into_iter().map(String::from).collect(),
+                description_hash: Some(Binary::from(sha256(name.as_bytes()))),
+                image_uris: vec![String::from("ipfs://image")]
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), msg).unwrap();
+        }
+        let msg = ExecuteMsg::SetGoodsMetadata {name: String::from("TV"), category: Some(String::from("toys")), tags: vec![], description_hash: None, image_uris: vec![]};
+        let res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), msg);
+        match res {
+            Err(ContractError::UnknownCategory {}) => {},
+            _ => panic!("Category does not exist!")
+        }
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GoodsByCategory {category: String::from("electronics"), start_after: None, limit: None}).unwrap();
+        let value: GoodsResponse = from_binary(&res).unwrap();
+        assert_eq!(vec!["Radio", "TV"], value.goods.iter().map(|x| x.name.as_str()).collect::<Vec<_>>());
+        assert_eq!(vec!["4k", "used"], value.goods[1].metadata.tags);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GoodsByTag {tag: String::from("USED"), start_after: Some(String::from("Radio")), limit: None}).unwrap();
+        let value: GoodsResponse = from_binary(&res).unwrap();
+        assert_eq!(vec!["TV"], value.goods.iter().map(|x| x.name.as_str()).collect::<Vec<_>>());
+
+        // moving a listing drops it from its old indexes
+        let msg = ExecuteMsg::SetGoodsMetadata {name: String::from("TV"), category: Some(String::from("furniture")), tags: vec![String::from("new")], description_hash: None, image_uris: vec![]};
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GoodsByCategory {category: String::from("electronics"), start_after: None, limit: None}).unwrap();
+        let value: GoodsResponse = from_binary(&res).unwrap();
+        assert_eq!(vec!["Radio"], value.goods.iter().map(|x| x.name.as_str()).collect::<Vec<_>>());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GoodsByTag {tag: String::from("used"), start_after: None, limit: None}).unwrap();
+        let value: GoodsResponse = from_binary(&res).unwrap();
+        assert_eq!(vec!["Radio"], value.goods.iter().map(|x| x.name.as_str()).collect::<Vec<_>>());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GoodsByTag {tag: String::from("new"), start_after: None, limit: Some(1)}).unwrap();
+        let value: GoodsResponse = from_binary(&res).unwrap();
+        assert_eq!(vec!["Sofa"], value.goods.iter().map(|x| x.name.as_str()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_buy() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("creator", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+
+        let info2 = mock_info("buyer", &coins(2000, "LUNA"));
+        let _res = execute(deps.as_mut(), mock_env(), info2, msg2).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrders {}).unwrap();
+        let value: OrdersResponse = from_binary(&res).unwrap();
+        println!("{:?}", value);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAddresses {id: 0u32, viewer: None}).unwrap();
+        let value: AddressesResponse = from_binary(&res).unwrap();
+        println!("{:?}", value);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        println!("{:?}", value);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("creator", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetGoods {}).unwrap();
+        let value: GoodsResponse = from_binary(&res).unwrap();
+        println!("{:?}", value);
+        assert_eq!(Uint128::from(200u32), value.goods[0].price.amount);
+
+        let msg2 = ExecuteMsg::Reset {
+            name: String::from("TV"),
+            price: 20
+        };
+        let info2 = mock_info("creator_fake", &coins(1000, "earth"));
+
+        let res = execute(deps.as_mut(), mock_env(), info2, msg2.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {},
+            _ => panic!("Not buyer, not authorized!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), info, msg2);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetGoods {}).unwrap();
+        let value: GoodsResponse = from_binary(&res).unwrap();
+        println!("{:?}", value);
+        assert_eq!(Uint128::from(20u32), value.goods[0].price.amount);
+    }
+
+    #[test]
+    fn test_checkout() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let _res = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        for (seller, name, price) in [("seller", "TV", 200), ("seller", "Radio", 50), ("seller2", "Sofa", 300)] {
+            let msg = ExecuteMsg::Post {
+                name: String::from(name),
+                price,
+                denom: String::from("LUNA"),
+                seller_area: String::from("Montreal")
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(seller, &[]), msg).unwrap();
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), ExecuteMsg::SetGoodsStock {name: String::from("TV"), stock: 2}).unwrap();
+
+        let cart = |items: &[(&str, u32)]| ExecuteMsg::Checkout {
+            items: items.iter().map(|(name, quantity)| CartItem {name: String::from(*name), quantity: *quantity}).collect(),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(800, "LUNA")), cart(&[("TV", 1), ("Sofa", 1)]));
+        match res {
+            Err(ContractError::InvalidCart {}) => {},
+            _ => panic!("Items of different sellers can not ship together!")
+        }
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(800, "LUNA")), cart(&[("TV", 3)]));
+        match res {
+            Err(ContractError::InsufficientStock {}) => {},
+            _ => panic!("Only two TVs are in stock!")
+        }
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(400, "LUNA")), cart(&[("TV", 2), ("Radio", 1)]));
+        match res {
+            Err(ContractError::InsufficientFundsSend {}) => {},
+            _ => panic!("The cart costs 450!")
+        }
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(450, "LUNA")), cart(&[("TV", 2), ("Radio", 1)])).unwrap();
+        assert_eq!("0", res.attributes[1].value);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(coin(450, "LUNA"), value.order.price);
+        assert_eq!(2, value.order.items.len());
+        assert_eq!(Ordered, value.order.goods.status);
+
+        // one shipping auction for the whole cart
+        register_shipper(deps.as_mut(), "shipper");
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &coins(450, "LUNA")), ExecuteMsg::TakeOrder {id: 0, pub_key: None, algorithm: None, price: coin(10, "LUNA")}).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), ExecuteMsg::ChooseBid {id: 0, shipper: String::from("shipper")}).unwrap();
+        for address in ["buyer", "seller"] {
+            let msg = ExecuteMsg::UploadAddress {id: 0, address_enc: String::from("my address").into_bytes()};
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(address, &[]), msg).unwrap();
+        }
+
+        // one of the TVs is refunded, the seller keeps the rest
+        let msg = ExecuteMsg::DisputeUnsatisfied {id: 0, items: vec![CartItem {name: String::from("Sofa"), quantity: 1}]};
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), msg);
+        match res {
+            Err(ContractError::InvalidCart {}) => {},
+            _ => panic!("Sofa is not part of the order!")
+        }
+        let msg = ExecuteMsg::DisputeUnsatisfied {id: 0, items: vec![CartItem {name: String::from("TV"), quantity: 1}]};
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), msg).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), ExecuteMsg::DisputeConfirm {id: 0}).unwrap();
+        for (address, amount) in [("buyer", 200), ("seller", 250)] {
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from(address)}).unwrap();
+            let value: ClaimableResponse = from_binary(&res).unwrap();
+            assert_eq!(vec![coin(amount, "LUNA")], value.claimable);
+        }
+        // the refunded TV is back on sale
+        for (name, status, stock) in [("TV", Available, 1), ("Radio", Sold, 0)] {
+            let good = GOODS_LIST.load(&deps.storage, name).unwrap();
+            assert_eq!((status, stock), (good.status, good.stock));
+        }
+    }
+
+    #[test]
+    fn test_dispute_restock() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let _res = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 100,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), msg).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), ExecuteMsg::SetGoodsStock {name: String::from("TV"), stock: 5}).unwrap();
+        register_shipper(deps.as_mut(), "shipper");
+
+        // two carts of two TVs each, the buyer disputes one TV of each
+        for (id, dispute) in [(0, ExecuteMsg::DisputeUnsatisfied {id: 0, items: vec![CartItem {name: String::from("TV"), quantity: 1}]}), (1, ExecuteMsg::DisputeBroken {id: 1, items: vec![CartItem {name: String::from("TV"), quantity: 1}]})] {
+            let msg = ExecuteMsg::Checkout {
+                items: vec![CartItem {name: String::from("TV"), quantity: 2}],
+                buyer_area: String::from("Montreal"),
+                delivery_lock: None
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(200, "LUNA")), msg).unwrap();
+            let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &coins(200, "LUNA")), ExecuteMsg::TakeOrder {id, pub_key: None, algorithm: None, price: coin(10, "LUNA")}).unwrap();
+            let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), ExecuteMsg::ChooseBid {id, shipper: String::from("shipper")}).unwrap();
+            for address in ["buyer", "seller"] {
+                let msg = ExecuteMsg::UploadAddress {id, address_enc: String::from("my address").into_bytes()};
+                let _res = execute(deps.as_mut(), mock_env(), mock_info(address, &[]), msg).unwrap();
+            }
+            let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), dispute).unwrap();
+            let stock = GOODS_LIST.load(&deps.storage, "TV").unwrap().stock;
+            // broken-goods claims wait for the contest period
+            let mut env = mock_env();
+            env.block.time = env.block.time.plus_seconds(CONTEST_PERIOD);
+            let _res = execute(deps.as_mut(), env, mock_info("seller", &[]), ExecuteMsg::DisputeConfirm {id}).unwrap();
+            let good = GOODS_LIST.load(&deps.storage, "TV").unwrap();
+            assert_eq!(Available, good.status);
+            // the refunded TV goes back into stock, the broken one was paid for by the shipper
+            assert_eq!(if id == 0 { stock + 1 } else { stock }, good.stock);
+        }
+        let good = GOODS_LIST.load(&deps.storage, "TV").unwrap();
+        assert_eq!((2, 0), (good.stock, good.reserved));
+    }
+
+    #[test]
+    fn test_batch() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::UpdateConfig {
+            min_shipper_stake: None,
+            collateral_bps: None,
+            insurance_bps: None,
+            arbiter: None,
+            max_batch_size: Some(2)
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let post = |name: &str| PostItem {name: String::from(name), price: 100, denom: String::from("LUNA"), seller_area: String::from("Montreal")};
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::BatchPost {items: vec![post("TV"), post("Radio"), post("Sofa")]});
+        match res {
+            Err(ContractError::InvalidBatchSize {}) => {},
+            _ => panic!("Batch is larger than the configured maximum!")
+        }
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::BatchPost {items: vec![post("TV"), post("Radio")]}).unwrap();
+        let attributes = res.attributes.iter().map(|x| (x.key.as_str(), x.value.as_str())).collect::<Vec<_>>();
+        assert_eq!(vec![("method", "try_batch_post"), ("item_0", "TV:100LUNA"), ("method", "try_post"), ("item_1", "Radio:100LUNA"), ("method", "try_post")], attributes);
+
+        let items = vec![ResetItem {name: String::from("TV"), price: 200}, ResetItem {name: String::from("Radio"), price: 50}];
+        let res = execute(deps.as_mut(), mock_env(), mock_info("seller2", &[]), ExecuteMsg::BatchReset {items: items.clone()});
+        match res {
+            Err(ContractError::Unauthorized {}) => {},
+            _ => panic!("Only the seller may reset their goods!")
+        }
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::BatchReset {items}).unwrap();
+        assert_eq!(("item_1", "Radio:50LUNA"), (res.attributes[3].key.as_str(), res.attributes[3].value.as_str()));
+
+        for (name, price) in [("TV", 200), ("Radio", 50)] {
+            let msg = ExecuteMsg::Buy {name: String::from(name), buyer_area: String::from("Montreal"), delivery_lock: None};
+            let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(price, "LUNA")), msg).unwrap();
+        }
+        register_shipper(deps.as_mut(), "shipper");
+        let take = |id: u32| TakeOrderItem {id, pub_key: None, algorithm: None, price: coin(10, "LUNA")};
+        // one bid's collateral does not cover both
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &coins(200, "LUNA")), ExecuteMsg::BatchTakeOrder {items: vec![take(0), take(1)]});
+        match res {
+            Err(ContractError::InsufficientFundsSend {}) => {},
+            _ => panic!("Collateral of 250 is required!")
+        }
+        // anything above the collateral is credited back
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &[coin(260, "LUNA"), coin(5, "earth")]), ExecuteMsg::BatchTakeOrder {items: vec![take(0), take(1)]}).unwrap();
+        assert_eq!(("item_0", "0:200LUNA"), (res.attributes[1].key.as_str(), res.attributes[1].value.as_str()));
+        assert_eq!(2, res.attributes.iter().filter(|x| x.value == "try_take_order").count());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("shipper")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(10, "LUNA"), coin(5, "earth")], value.claimable);
+        for (id, deposit) in [(0, 200), (1, 50)] {
+            let order = ORDER_LIST.load(&deps.storage, &id.to_string()).unwrap();
+            assert_eq!(Bidding, order.status);
+            assert_eq!(coin(deposit, "LUNA"), order.shipper_bids[0].deposit);
+        }
+    }
+
+    #[test]
+    fn test_auction() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::PostAuction {
+            name: String::from("TV"),
+            reserve_price: 100,
+            min_increment: 10,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal"),
+            end_time: mock_env().block.time.plus_seconds(100).seconds()
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer1", &coins(2000, "LUNA")), msg2);
+        match res {
+            Err(ContractError::WrongListingType {}) => {},
+            _ => panic!("Auctions cannot be bought directly!")
+        }
+
+        let msg3 = ExecuteMsg::Bid {
+            name: String::from("TV"),
+            buyer_area: String::from("Ottawa")
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer1", &coins(90, "LUNA")), msg3.clone());
+        match res {
+            Err(ContractError::BidTooLow {}) => {},
+            _ => panic!("Bid is below the reserve price!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer1", &coins(100, "LUNA")), msg3.clone()).unwrap();
+
+        let msg4 = ExecuteMsg::Bid {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal")
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer2", &coins(105, "LUNA")), msg4.clone());
+        match res {
+            Err(ContractError::BidTooLow {}) => {},
+            _ => panic!("Bid is below the minimum increment!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer2", &coins(110, "LUNA")), msg4).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("buyer1")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(100, "LUNA")], value.claimable);
+
+        let msg5 = ExecuteMsg::Finalize {
+            name: String::from("TV")
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg5.clone());
+        match res {
+            Err(ContractError::AuctionNotEnded {}) => {},
+            _ => panic!("Auction is still running!")
+        }
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(100);
+        let _res = execute(deps.as_mut(), env, mock_info("anyone", &[]), msg5).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(Addr::unchecked("buyer2"), value.order.buyer);
+        assert_eq!(coin(110, "LUNA"), value.order.price);
+        assert_eq!(String::from("Montreal"), value.order.buyer_area);
+        assert_eq!(Ordered, value.order.goods.status);
+    }
+
+    #[test]
+    fn test_repost() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let auction = ExecuteMsg::PostAuction {
+            name: String::from("TV"),
+            reserve_price: 100,
+            min_increment: 10,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal"),
+            end_time: mock_env().block.time.plus_seconds(100).seconds()
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), auction.clone()).unwrap();
+        // the seller may still change a listing nobody bid on
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), auction).unwrap();
+        let post = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 1,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("attacker", &[]), post.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {},
+            _ => panic!("Only the seller can replace a listing!")
+        }
+
+        let msg = ExecuteMsg::Bid {name: String::from("TV"), buyer_area: String::from("Montreal")};
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(100, "LUNA")), msg).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), post.clone());
+        match res {
+            Err(ContractError::GoodsNotAvailable {}) => {},
+            _ => panic!("The highest bid is still escrowed!")
+        }
+        let good = GOODS_LIST.load(&deps.storage, "TV").unwrap();
+        match good.listing {
+            Listing::EnglishAuction(auction) => assert_eq!(Addr::unchecked("buyer"), auction.highest_bid.unwrap().bidder),
+            _ => panic!("Listing was replaced!")
+        }
+
+        // open offers hold escrow as well
+        let post = ExecuteMsg::Post {
+            name: String::from("Radio"),
+            price: 50,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), post.clone()).unwrap();
+        let msg = ExecuteMsg::MakeOffer {name: String::from("Radio"), buyer_area: String::from("Montreal"), expires_in: 100};
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(40, "LUNA")), msg).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), post);
+        match res {
+            Err(ContractError::GoodsNotAvailable {}) => {},
+            _ => panic!("An offer is still escrowed!")
+        }
+
+        // a checkout refunds expired offers, the units it holds keep the listing from being replaced
+        let post = ExecuteMsg::Post {
+            name: String::from("Lamp"),
+            price: 30,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), post.clone()).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::SetGoodsStock {name: String::from("Lamp"), stock: 3}).unwrap();
+        let msg = ExecuteMsg::MakeOffer {name: String::from("Lamp"), buyer_area: String::from("Montreal"), expires_in: 10};
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer2", &coins(20, "LUNA")), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(20);
+        let msg = ExecuteMsg::Checkout {
+            items: vec![CartItem {name: String::from("Lamp"), quantity: 1}],
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("buyer", &coins(30, "LUNA")), msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("buyer2")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(20, "LUNA")], value.claimable);
+        let good = GOODS_LIST.load(&deps.storage, "Lamp").unwrap();
+        assert_eq!((Available, 2, 1), (good.status, good.stock, good.reserved));
+        let res = execute(deps.as_mut(), env, info, post);
+        match res {
+            Err(ContractError::GoodsNotAvailable {}) => {},
+            _ => panic!("An open order still holds a unit!")
+        }
+    }
+
+    #[test]
+    fn test_dutch_auction() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::PostDutchAuction {
+            name: String::from("TV"),
+            start_price: 200,
+            floor_price: 100,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal"),
+            duration: 100
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(50);
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetPrice {name: String::from("TV")}).unwrap();
+        let value: PriceResponse = from_binary(&res).unwrap();
+        assert_eq!(coin(150, "LUNA"), value.price);
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+        let res = execute(deps.as_mut(), env, mock_info("buyer", &coins(200, "LUNA")), msg2).unwrap();
+        assert_eq!(0, res.messages.len());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("buyer")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(50, "LUNA")], value.claimable);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(coin(150, "LUNA"), value.order.price);
+
+        // a free floor can be bought without sending any funds
+        let msg = ExecuteMsg::PostDutchAuction {
+            name: String::from("Radio"),
+            start_price: 100,
+            floor_price: 0,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal"),
+            duration: 100
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(100);
+        let msg = ExecuteMsg::Buy {name: String::from("Radio"), buyer_area: String::from("Montreal"), delivery_lock: None};
+        let _res = execute(deps.as_mut(), env, mock_info("buyer2", &[]), msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 1u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(coin(0, "LUNA"), value.order.price);
+    }
+
+    #[test]
+    fn test_offers() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg2 = ExecuteMsg::MakeOffer {
+            name: String::from("TV"),
+            buyer_area: String::from("Ottawa"),
+            expires_in: 100
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer1", &coins(120, "LUNA")), msg2).unwrap();
+        let msg3 = ExecuteMsg::MakeOffer {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            expires_in: 100
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer2", &coins(150, "LUNA")), msg3).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOffers {name: String::from("TV")}).unwrap();
+        let value: OffersResponse = from_binary(&res).unwrap();
+        assert_eq!(2, value.offers.len());
+
+        let msg4 = ExecuteMsg::CounterOffer {
+            name: String::from("TV"),
+            offer_id: 1,
+            price: 180,
+            expires_in: 100
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer2", &[]), msg4.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {},
+            _ => panic!("Only the seller can counter!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), info, msg4).unwrap();
+
+        let msg5 = ExecuteMsg::AcceptCounterOffer {
+            name: String::from("TV"),
+            offer_id: 1
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer2", &coins(10, "LUNA")), msg5.clone());
+        match res {
+            Err(ContractError::InsufficientFundsSend {}) => {},
+            _ => panic!("Counter price is not covered!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer2", &coins(30, "LUNA")), msg5).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(Addr::unchecked("buyer2"), value.order.buyer);
+        assert_eq!(coin(180, "LUNA"), value.order.price);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOffers {name: String::from("TV")}).unwrap();
+        let value: OffersResponse = from_binary(&res).unwrap();
+        assert_eq!(0, value.offers.len());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("buyer1")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(120, "LUNA")], value.claimable);
+    }
+
+    #[test]
+    fn test_offer_expiry() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        // offers are listed by id, not by its string form
+        for i in 0..12u128 {
+            let msg = ExecuteMsg::MakeOffer {name: String::from("TV"), buyer_area: String::from("Montreal"), expires_in: if i == 0 { 10 } else { 100 }};
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(&format!("buyer{}", i), &coins(100 + i, "LUNA")), msg).unwrap();
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOffers {name: String::from("TV")}).unwrap();
+        let value: OffersResponse = from_binary(&res).unwrap();
+        assert_eq!((0..12).collect::<Vec<u32>>(), value.offers.iter().map(|x| x.id).collect::<Vec<_>>());
+
+        // the seller touching the listing refunds offers that expired
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(10);
+        let _res = execute(deps.as_mut(), env, info, ExecuteMsg::Reset {name: String::from("TV"), price: 150}).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("buyer0")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(100, "LUNA")], value.claimable);
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer0", &[]), ExecuteMsg::CancelOffer {name: String::from("TV"), offer_id: 0});
+        match res {
+            Err(ContractError::OfferNotAvailable {}) => {},
+            _ => panic!("Expired offer was already refunded!")
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("buyer1")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert!(value.claimable.is_empty());
+    }
+
+    #[test]
+    fn test_shipper_registry() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // without a minimum the stake denom comes from the funds, which then have to agree on it
+        let msg = ExecuteMsg::RegisterShipper {
+            service_areas: vec![String::from("Montreal")],
+            pub_key: String::from(SHIPPER_KEY_2),
+            key_algorithm: None,
+            capacity: 1,
+            rate_per_km: None,
+            max_radius_km: None
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper2", &[coin(50, "LUNA"), coin(5, "earth")]), msg);
+        match res {
+            Err(ContractError::DenomNotAccepted {}) => {},
+            _ => panic!("Stake has to be in a single denom!")
+        }
+
+        let msg = ExecuteMsg::UpdateConfig {
+            min_shipper_stake: Some(coin(50, "LUNA")),
+            collateral_bps: None,
+            insurance_bps: None,
+            arbiter: None,
+            max_batch_size: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Ottawa"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(2000, "LUNA")), msg2).unwrap();
+
+        let msg3 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: None,
+            algorithm: None,
+            price: coin(10, "LUNA")
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(200, "LUNA")), msg3.clone());
+        match res {
+            Err(ContractError::ShipperNotRegistered {}) => {},
+            _ => panic!("Shipper is not registered!")
+        }
+
+        let msg4 = ExecuteMsg::RegisterShipper {
+            service_areas: vec![String::from("Montreal")],
+            pub_key: String::from(SHIPPER_KEY_1),
+            key_algorithm: None,
+            capacity: 1,
+            rate_per_km: None,
+            max_radius_km: None
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(20, "LUNA")), msg4.clone());
+        match res {
+            Err(ContractError::InsufficientStake {}) => {},
+            _ => panic!("Stake is below the minimum!")
+        }
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &[coin(50, "LUNA"), coin(5, "earth")]), msg4.clone());
+        match res {
+            Err(ContractError::DenomNotAccepted {}) => {},
+            _ => panic!("Only the stake denom is accepted!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(50, "LUNA")), msg4).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(200, "LUNA")), msg3.clone());
+        match res {
+            Err(ContractError::AreaNotServed {}) => {},
+            _ => panic!("Shipper does not serve Ottawa!")
+        }
+
+        let msg5 = ExecuteMsg::RegisterShipper {
+            service_areas: vec![String::from("Montreal"), String::from("Ottawa")],
+            pub_key: String::from(SHIPPER_KEY_1),
+            key_algorithm: None,
+            capacity: 1,
+            rate_per_km: None,
+            max_radius_km: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &[]), msg5).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(200, "LUNA")), msg3).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetShippers {area: String::from("Ottawa")}).unwrap();
+        let value: ShippersResponse = from_binary(&res).unwrap();
+        assert_eq!(1, value.shippers.len());
+        assert_eq!(coin(50, "LUNA"), value.shippers[0].stake);
+
+        let msg6 = ExecuteMsg::ChooseBid {
+            id: 0,
+            shipper: String::from("shipper1")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), msg6).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(String::from(SHIPPER_KEY_1), value.order.shipper_key);
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &[]), ExecuteMsg::UnregisterShipper {});
+        match res {
+            Err(ContractError::ShipperBusy {}) => {},
+            _ => panic!("Shipper still has an active order!")
+        }
+    }
+
+    #[test]
+    fn test_shipping_distance() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::SetGoodsLocation {
+            name: String::from("TV"),
+            location: Location { latitude: 45_501_689, longitude: -73_567_256 }
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Ottawa"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(2000, "LUNA")), msg2).unwrap();
+        let msg2 = ExecuteMsg::SetBuyerLocation {
+            id: 0,
+            location: Location { latitude: 45_421_530, longitude: -75_697_193 }
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), msg2).unwrap();
+
+        for (shipper, max_radius_km) in [("shipper1", 100), ("shipper2", 200)] {
+            let msg3 = ExecuteMsg::RegisterShipper {
+                service_areas: vec![String::from("Montreal"), String::from("Ottawa")],
+                pub_key: String::from(SHIPPER_KEY),
+                key_algorithm: None,
+                capacity: 10,
+                rate_per_km: Some(coin(2, "LUNA")),
+                max_radius_km: Some(max_radius_km)
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(shipper, &coins(100, "LUNA")), msg3).unwrap();
+        }
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetShippingEstimates {id: 0u32}).unwrap();
+        let value: ShippingEstimatesResponse = from_binary(&res).unwrap();
+        // Montreal to Ottawa is about 166 km
+        assert!(value.distance_m > 165_000 && value.distance_m < 168_000);
+        assert_eq!(1, value.estimates.len());
+        assert_eq!(Addr::unchecked("shipper2"), value.estimates[0].shipper);
+        assert_eq!(coin(2 * u128::from(value.distance_m.div_ceil(1000)), "LUNA"), value.estimates[0].fee);
+
+        let msg4 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: None,
+            algorithm: None,
+            price: coin(10, "LUNA")
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(200, "LUNA")), msg4.clone());
+        match res {
+            Err(ContractError::OutOfRange {}) => {},
+            _ => panic!("Order is beyond the shipper's radius!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper2", &coins(200, "LUNA")), msg4).unwrap();
+    }
+
+    #[test]
+    fn test_take_order() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("creator", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+
+        let info2 = mock_info("buyer", &coins(2000, "LUNA"));
+        let _res = execute(deps.as_mut(), mock_env(), info2, msg2).unwrap();
+
+        register_shipper(deps.as_mut(), "shipper1");
+        register_shipper(deps.as_mut(), "shipper2");
+
+        let weak_rsa_key = "-----BEGIN PUBLIC KEY-----
+MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQDZX3qNCX66UBU9HVfqp3WbSaNe
+WSYYsiRNCTRiBWWEeq93ZQSTdvTiEW1rkLTOkLr7FYW+WaeffsQXNziRQEOOWHAY
+XcYQFiuDDCiz1jmfXSI5muvFMcZlsvZ4HD5R+k31AuuuPudLn9d2NjYjA0EHay/y
+QYWiujhoFJAAs/W3DwIDAQAB
+-----END PUBLIC KEY-----
+";
+        let x25519_key = "-----BEGIN PUBLIC KEY-----
+MCowBQYDK2VuAyEACB/Q+L6PVhkVBHZJNyjmdQEysZ9jJIGoacw3xBcvCjM=
+-----END PUBLIC KEY-----
+";
+        for pub_key in ["rsa1", weak_rsa_key, x25519_key] {
+            let msg = ExecuteMsg::TakeOrder {
+                id: 0,
+                pub_key: Some(String::from(pub_key)),
+                algorithm: None,
+                price: coin(10, "LUNA")
+            };
+            let res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(2000, "LUNA")), msg);
+            match res {
+                Err(ContractError::InvalidPublicKey {}) => {},
+                _ => panic!("Shipper key must be an RSA key of at least 2048 bits!")
+            }
+        }
+
+        let msg3 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: Some(String::from(SHIPPER_KEY_1)),
+            algorithm: None,
+            price: coin(10, "LUNA")
+        };
+        let info3 = mock_info("shipper1", &coins(2000, "LUNA"));
+        let _res = execute(deps.as_mut(), mock_env(), info3, msg3).unwrap();
+
+        let msg4 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: Some(String::from(SHIPPER_KEY_2)),
+            algorithm: None,
+            price: coin(11, "LUNA")
+        };
+        let info4 = mock_info("shipper2", &coins(5000, "LUNA"));
+        let _res = execute(deps.as_mut(), mock_env(), info4, msg4).unwrap();
+
+        let msg33 = ExecuteMsg::ChooseBid {
+            id: 0,
+            shipper: String::from("shipper1")
+        };
+        let info33 = mock_info("buyer", &coins(1000, "LUNA"));
+        let _res = execute(deps.as_mut(), mock_env(), info33, msg33).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrders {}).unwrap();
+        let value_all: OrdersResponse = from_binary(&res).unwrap();
+        println!("{:?}", value_all);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value_order_0: OrderDetailResponse = from_binary(&res).unwrap();
+        println!("{:?}", value_order_0);
+
+        assert_eq!(value_all.orders[0], value_order_0.order)
+
+
+    }
+
+    #[test]
+    fn test_sealed_bidding() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(2000, "LUNA")), msg2).unwrap();
+
+        let msg3 = ExecuteMsg::SealBidding {
+            id: 0,
+            commit_period: 100,
+            reveal_period: 100,
+            deposit: coin(5, "LUNA")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), msg3).unwrap();
+
+        register_shipper(deps.as_mut(), "shipper1");
+        register_shipper(deps.as_mut(), "shipper2");
+
+        let msg4 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: Some(String::from(SHIPPER_KEY_1)),
+            algorithm: None,
+            price: coin(10, "LUNA")
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(2000, "LUNA")), msg4);
+        match res {
+            Err(ContractError::WrongBiddingMode {}) => {},
+            _ => panic!("Sealed orders only take commitments!")
+        }
+
+        let commitment1 = bid_commitment(&Addr::unchecked("shipper1"), &coin(10, "LUNA"), "salt1", SHIPPER_KEY_1, EncryptionAlgorithm::RsaPkcs1v15);
+        let commitment2 = bid_commitment(&Addr::unchecked("shipper2"), &coin(8, "LUNA"), "salt2", SHIPPER_KEY_2, EncryptionAlgorithm::RsaPkcs1v15);
+        for (shipper, commitment) in [("shipper1", commitment1), ("shipper2", commitment2)] {
+            let msg5 = ExecuteMsg::CommitBid {
+                id: 0,
+                commitment: Binary::from(commitment)
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(shipper, &coins(5, "LUNA")), msg5).unwrap();
+        }
+
+        let msg6 = ExecuteMsg::RevealBid {
+            id: 0,
+            pub_key: String::from(SHIPPER_KEY_1),
+            algorithm: None,
+            price: coin(10, "LUNA"),
+            salt: String::from("salt1")
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(200, "LUNA")), msg6.clone());
+        match res {
+            Err(ContractError::NotInRevealPeriod {}) => {},
+            _ => panic!("Commit period is still running!")
+        }
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(150);
+        let msg7 = ExecuteMsg::RevealBid {
+            id: 0,
+            pub_key: String::from(SHIPPER_KEY_1),
+            algorithm: None,
+            price: coin(9, "LUNA"),
+            salt: String::from("salt1")
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("shipper1", &coins(200, "LUNA")), msg7);
+        match res {
+            Err(ContractError::CommitmentMismatch {}) => {},
+            _ => panic!("Revealed price differs from the commitment!")
+        }
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("shipper1", &coins(200, "LUNA")), msg6).unwrap();
+
+        let msg8 = ExecuteMsg::ChooseBid {
+            id: 0,
+            shipper: String::from("shipper1")
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("buyer", &coins(20, "LUNA")), msg8.clone());
+        match res {
+            Err(ContractError::RevealPeriodNotEnded {}) => {},
+            _ => panic!("Reveal period is still running!")
+        }
+        env.block.time = env.block.time.plus_seconds(50);
+        let _res = execute(deps.as_mut(), env, mock_info("buyer", &coins(20, "LUNA")), msg8).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(WaitingAddressUpload, value.order.status);
+        assert_eq!(1, value.order.shipper_bids.len());
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("shipper1")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(5, "LUNA")], value.claimable);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("buyer")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(5, "LUNA")], value.claimable);
+    }
+
+    #[test]
+    fn test_unseal_bidding() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(2000, "LUNA")), msg2).unwrap();
+
+        let msg3 = ExecuteMsg::SealBidding {
+            id: 0,
+            commit_period: 100,
+            reveal_period: 100,
+            deposit: coin(5, "LUNA")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), msg3).unwrap();
+
+        let msg4 = ExecuteMsg::UnsealBidding {id: 0};
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), msg4.clone());
+        match res {
+            Err(ContractError::BiddingNotEnded {}) => {},
+            _ => panic!("Commit period is still running!")
+        }
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(100);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("seller", &[]), msg4.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {},
+            _ => panic!("Only the buyer unseals!")
+        }
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("buyer", &[]), msg4).unwrap();
+
+        register_shipper(deps.as_mut(), "shipper1");
+        let msg5 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: Some(String::from(SHIPPER_KEY_1)),
+            algorithm: None,
+            price: coin(10, "LUNA")
+        };
+        let _res = execute(deps.as_mut(), env, mock_info("shipper1", &coins(200, "LUNA")), msg5).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(BiddingMode::Open, value.order.bidding);
+        assert_eq!(Bidding, value.order.status);
+    }
+
+    #[test]
+    fn test_auto_select() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(2000, "LUNA")), msg2).unwrap();
+
+        let msg3 = ExecuteMsg::AutoSelect {
+            id: 0,
+            bidding_period: 100,
+            policy: SelectionPolicy::LowestPrice,
+            max_fee: coin(10, "LUNA")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), msg3).unwrap();
+
+        for shipper in ["shipper1", "shipper2", "shipper3"] {
+            register_shipper(deps.as_mut(), shipper);
+        }
+
+        for (shipper, price) in [("shipper1", 10), ("shipper2", 8), ("shipper3", 5)] {
+            let msg4 = ExecuteMsg::TakeOrder {
+                id: 0,
+                pub_key: Some(String::from(SHIPPER_KEY)),
+                algorithm: None,
+                price: coin(price, if shipper == "shipper3" { "earth" } else { "LUNA" })
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(shipper, &coins(200, "LUNA")), msg4).unwrap();
+        }
+
+        let msg5 = ExecuteMsg::SelectBid {
+            id: 0
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg5.clone());
+        match res {
+            Err(ContractError::BiddingNotEnded {}) => {},
+            _ => panic!("Bidding window is still open!")
+        }
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(100);
+        let _res = execute(deps.as_mut(), env, mock_info("anyone", &[]), msg5).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(WaitingAddressUpload, value.order.status);
+        assert_eq!(Addr::unchecked("shipper2"), value.order.shipper);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("buyer")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(4, "LUNA")], value.claimable);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("shipper1")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(200, "LUNA")], value.claimable);
+    }
+
+    #[test]
+    fn test_bid_score_weights() {
+        let deps = mock_dependencies(&[]);
+        let bid = ShipperBid {
+            shipper: Addr::unchecked("shipper"),
+            pub_key: String::from(SHIPPER_KEY),
+            algorithm: EncryptionAlgorithm::RsaPkcs1v15,
+            price: coin(5, "LUNA"),
+            deposit: coin(200, "LUNA")
+        };
+        let auto_select = |price_weight, reputation_weight| AutoSelect {
+            bidding_end: mock_env().block.time,
+            policy: SelectionPolicy::Weighted {price_weight, reputation_weight},
+            max_fee: coin(10, "LUNA")
+        };
+        // half the max fee scores 50, a shipper without history has a neutral reputation
+        let neutral = shipper_reputation(&deps.storage, &mock_env(), &bid.shipper).unwrap();
+        assert_eq!(2 * 50 + 3 * neutral, bid_score(&deps.storage, &mock_env(), &auto_select(2, 3), &bid).unwrap());
+        assert_eq!(u64::MAX, bid_score(&deps.storage, &mock_env(), &auto_select(u64::MAX, u64::MAX), &bid).unwrap());
+    }
+
+    #[test]
+    fn test_select_bid_by_reputation() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(2000, "LUNA")), msg2).unwrap();
+
+        let msg3 = ExecuteMsg::AutoSelect {
+            id: 0,
+            bidding_period: 100,
+            policy: SelectionPolicy::BestReputation,
+            max_fee: coin(10, "LUNA")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), msg3).unwrap();
+
+        // the cheaper shipper lost its disputes, the other one delivered and was rated well
+        for event in [ReputationEvent::DisputeLost, ReputationEvent::DisputeLost] {
+            reputation::record(deps.as_mut().storage, &Addr::unchecked("shipper1"), mock_env().block.time, event).unwrap();
+        }
+        for event in [ReputationEvent::Completed(coin(10, "LUNA")), ReputationEvent::Rated(5)] {
+            reputation::record(deps.as_mut().storage, &Addr::unchecked("shipper2"), mock_env().block.time, event).unwrap();
+        }
+
+        for (shipper, price) in [("shipper1", 5), ("shipper2", 8)] {
+            register_shipper(deps.as_mut(), shipper);
+            let msg4 = ExecuteMsg::TakeOrder {
+                id: 0,
+                pub_key: Some(String::from(SHIPPER_KEY)),
+                algorithm: None,
+                price: coin(price, "LUNA")
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(shipper, &coins(200, "LUNA")), msg4).unwrap();
+        }
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(100);
+        let _res = execute(deps.as_mut(), env, mock_info("anyone", &[]), ExecuteMsg::SelectBid {id: 0}).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(Addr::unchecked("shipper2"), value.order.shipper);
+        assert_eq!(coin(8, "LUNA"), value.order.shipping_fee);
+    }
+
+    #[test]
+    fn test_cancel_auto_select() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(2000, "LUNA")), msg2).unwrap();
+
+        let msg3 = ExecuteMsg::AutoSelect {
+            id: 0,
+            bidding_period: 100,
+            policy: SelectionPolicy::LowestPrice,
+            max_fee: coin(10, "LUNA")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), msg3).unwrap();
+
+        // the only bid is above the maximum fee
+        register_shipper(deps.as_mut(), "shipper1");
+        let msg4 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: Some(String::from(SHIPPER_KEY)),
+            algorithm: None,
+            price: coin(15, "LUNA")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(200, "LUNA")), msg4).unwrap();
+
+        let msg5 = ExecuteMsg::CancelAutoSelect {
+            id: 0
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), msg5.clone());
+        match res {
+            Err(ContractError::BiddingNotEnded {}) => {},
+            _ => panic!("Bidding window is still open!")
+        }
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(100);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), msg5.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {},
+            _ => panic!("Only the buyer can cancel!")
+        }
+        let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::SelectBid {id: 0});
+        match res {
+            Err(ContractError::ShipperNotFound {}) => {},
+            _ => panic!("No bid is within the maximum fee!")
+        }
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("buyer", &[]), msg5.clone()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("buyer")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(20, "LUNA")], value.claimable);
+
+        // the escrow can only be reclaimed once, the buyer picks a shipper by hand from here
+        let res = execute(deps.as_mut(), env, mock_info("buyer", &[]), msg5);
+        match res {
+            Err(ContractError::WrongBiddingMode {}) => {},
+            _ => panic!("Auto-select was already cancelled!")
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(Bidding, value.order.status);
+    }
+
+    #[test]
+    fn test_cancel_auto_select_with_bids() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(2000, "LUNA")), msg2).unwrap();
+
+        let msg3 = ExecuteMsg::AutoSelect {
+            id: 0,
+            bidding_period: 100,
+            policy: SelectionPolicy::LowestPrice,
+            max_fee: coin(10, "LUNA")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), msg3).unwrap();
+
+        register_shipper(deps.as_mut(), "shipper1");
+        let msg4 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: Some(String::from(SHIPPER_KEY)),
+            algorithm: None,
+            price: coin(8, "LUNA")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(200, "LUNA")), msg4).unwrap();
+
+        // a qualifying bid has to go through SelectBid
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(100);
+        let res = execute(deps.as_mut(), env, mock_info("buyer", &[]), ExecuteMsg::CancelAutoSelect {id: 0});
+        match res {
+            Err(ContractError::BidsAvailable {}) => {},
+            _ => panic!("A bid can still be selected!")
+        }
+    }
+
+    #[test]
+    fn test_relay_route() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Ottawa"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(2000, "LUNA")), msg2).unwrap();
+
+        for (shipper, price) in [("shipper1", 10), ("shipper2", 20), ("shipper3", 30)] {
+            register_shipper(deps.as_mut(), shipper);
+            let msg3 = ExecuteMsg::TakeOrder {
+                id: 0,
+                pub_key: None,
+                algorithm: None,
+                price: coin(price, "LUNA")
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(shipper, &coins(200, "LUNA")), msg3).unwrap();
+        }
+
+        let msg4 = ExecuteMsg::ChooseRoute {
+            id: 0,
+            shippers: vec![String::from("shipper1"), String::from("shipper2"), String::from("shipper3")]
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(100, "LUNA")), msg4.clone());
+        match res {
+            Err(ContractError::InsufficientFundsSend {}) => {},
+            _ => panic!("Buyer must deposit twice the total fee!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(120, "LUNA")), msg4).unwrap();
+
+        for sender in ["buyer", "seller"] {
+            let msg5 = ExecuteMsg::UploadAddress {
+                id: 0,
+                address_enc: String::from("my address").into_bytes()
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg5).unwrap();
+        }
+
+        for shipper in ["shipper1", "shipper2"] {
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(shipper, &[]), ExecuteMsg::ConfirmHandoff {id: 0}).unwrap();
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(1, value.order.custody_leg);
+        assert_eq!(Addr::unchecked("shipper2"), value.order.shipper);
+        assert_eq!(coin(60, "LUNA"), value.order.shipping_fee);
+
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper3", &[]), ExecuteMsg::ReportHandoffDamage {id: 0}).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), ExecuteMsg::DisputeConfirm {id: 0});
+        match res {
+            Err(ContractError::ContestPeriodNotEnded {}) => {},
+            _ => panic!("shipper2 may still contest the claim!")
+        }
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(CONTEST_PERIOD);
+        let _res = execute(deps.as_mut(), env, mock_info("seller", &[]), ExecuteMsg::DisputeConfirm {id: 0}).unwrap();
+
+        // shipper1 handed the package on and keeps its fee, the buyer gets back the other 110 of its deposit
+        for (address, claimable) in [("buyer", vec![coin(310, "LUNA")]), ("seller", vec![coin(200, "LUNA")]), ("shipper1", vec![coin(210, "LUNA")]), ("shipper2", vec![]), ("shipper3", vec![coin(200, "LUNA")])] {
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from(address)}).unwrap();
+            let value: ClaimableResponse = from_binary(&res).unwrap();
+            assert_eq!(claimable, value.claimable);
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetReputation {address: String::from("shipper2")}).unwrap();
+        let value: ReputationResponse = from_binary(&res).unwrap();
+        assert_eq!(0, value.score.reliability);
+    }
+
+    #[test]
+    fn test_insurance_pool() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::UpdateConfig {
+            min_shipper_stake: None,
+            collateral_bps: Some(5_000),
+            insurance_bps: Some(10_001),
+            arbiter: None,
+            max_batch_size: None
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        match res {
+            Err(ContractError::InvalidConfig {}) => {},
+            _ => panic!("Insurance share can not exceed the slashed collateral!")
+        }
+        let msg = ExecuteMsg::UpdateConfig {
+            min_shipper_stake: None,
+            collateral_bps: Some(5_000),
+            insurance_bps: Some(1_000),
+            arbiter: None,
+            max_batch_size: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("insurer", &coins(50, "LUNA")), ExecuteMsg::FundInsurance {}).unwrap();
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Ottawa"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(200, "LUNA")), msg2).unwrap();
+
+        register_shipper(deps.as_mut(), "shipper1");
+        let msg3 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: None,
+            algorithm: None,
+            price: coin(10, "LUNA")
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(99, "LUNA")), msg3.clone());
+        match res {
+            Err(ContractError::InsufficientFundsSend {}) => {},
+            _ => panic!("Shipper must post the configured collateral!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(100, "LUNA")), msg3).unwrap();
+
+        let msg4 = ExecuteMsg::ChooseBid {
+            id: 0,
+            shipper: String::from("shipper1")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), msg4).unwrap();
+        for sender in ["buyer", "seller"] {
+            let msg5 = ExecuteMsg::UploadAddress {
+                id: 0,
+                address_enc: String::from("my address").into_bytes()
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg5).unwrap();
+        }
+
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), ExecuteMsg::DisputeBroken {id: 0, items: vec![]}).unwrap();
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(CONTEST_PERIOD);
+        let _res = execute(deps.as_mut(), env, mock_info("seller", &[]), ExecuteMsg::DisputeConfirm {id: 0}).unwrap();
+
+        // 10 of the slashed 100 goes to the pool, the buyer gets the other 90 plus the 60 the pool holds
+        for (address, claimable) in [("buyer", vec![coin(170, "LUNA")]), ("seller", vec![coin(200, "LUNA")]), ("shipper1", vec![])] {
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from(address)}).unwrap();
+            let value: ClaimableResponse = from_binary(&res).unwrap();
+            assert_eq!(claimable, value.claimable);
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::InsurancePool {}).unwrap();
+        let value: InsurancePoolResponse = from_binary(&res).unwrap();
+        assert_eq!(Vec::<Coin>::new(), value.balance);
+    }
+
+    #[test]
+    fn test_contest_damage() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::UpdateConfig {
+            min_shipper_stake: None,
+            collateral_bps: None,
+            insurance_bps: None,
+            arbiter: Some(String::from("arbiter")),
+            max_batch_size: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(200, "LUNA")), msg2).unwrap();
+
+        register_shipper(deps.as_mut(), "shipper");
+        let msg3 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: None,
+            algorithm: None,
+            price: coin(10, "LUNA")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &coins(200, "LUNA")), msg3).unwrap();
+        let msg4 = ExecuteMsg::ChooseBid {
+            id: 0,
+            shipper: String::from("shipper")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), msg4).unwrap();
+        for sender in ["buyer", "seller"] {
+            let msg5 = ExecuteMsg::UploadAddress {
+                id: 0,
+                address_enc: String::from("my address").into_bytes()
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg5).unwrap();
+        }
+
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), ExecuteMsg::DisputeBroken {id: 0, items: vec![]}).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), ExecuteMsg::ContestDamage {id: 0});
+        match res {
+            Err(ContractError::Unauthorized {}) => {},
+            _ => panic!("Only the blamed shipper contests!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &[]), ExecuteMsg::ContestDamage {id: 0}).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(CONTEST_PERIOD);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("seller", &[]), ExecuteMsg::DisputeConfirm {id: 0});
+        match res {
+            Err(ContractError::DisputeContested {}) => {},
+            _ => panic!("Contested claims are settled by the arbiter!")
+        }
+        let res = execute(deps.as_mut(), env.clone(), mock_info("seller", &[]), ExecuteMsg::ResolveDamage {id: 0, upheld: true});
+        match res {
+            Err(ContractError::Unauthorized {}) => {},
+            _ => panic!("Only the arbiter resolves!")
+        }
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("arbiter", &[]), ExecuteMsg::ResolveDamage {id: 0, upheld: false}).unwrap();
+        let order = ORDER_LIST.load(&deps.storage, "0").unwrap();
+        assert_eq!(Shipping, order.status);
+        assert!(!order.contested);
+
+        // a second claim is contested too late and the seller confirms it
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("buyer", &[]), ExecuteMsg::DisputeBroken {id: 0, items: vec![]}).unwrap();
+        env.block.time = env.block.time.plus_seconds(CONTEST_PERIOD);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("shipper", &[]), ExecuteMsg::ContestDamage {id: 0});
+        match res {
+            Err(ContractError::ContestPeriodEnded {}) => {},
+            _ => panic!("Contest period is over!")
+        }
+        let _res = execute(deps.as_mut(), env, mock_info("seller", &[]), ExecuteMsg::DisputeConfirm {id: 0}).unwrap();
+        for (address, claimable) in [("buyer", vec![coin(220, "LUNA")]), ("seller", vec![coin(200, "LUNA")]), ("shipper", vec![])] {
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from(address)}).unwrap();
+            let value: ClaimableResponse = from_binary(&res).unwrap();
+            assert_eq!(claimable, value.claimable);
+        }
+    }
+
+    #[test]
+    fn test_upload_address() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+
+        let info2 = mock_info("buyer", &coins(2000, "LUNA"));
+        let _res = execute(deps.as_mut(), mock_env(), info2, msg2).unwrap();
+
+        register_shipper(deps.as_mut(), "shipper");
+
+        let msg3 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: Some(String::from(SHIPPER_KEY_1)),
+            algorithm: None,
+            price: coin(10, "LUNA")
+        };
+        let info3 = mock_info("shipper", &coins(2000, "LUNA"));
+        let _res = execute(deps.as_mut(), mock_env(), info3, msg3).unwrap();
+
+        let msg33 = ExecuteMsg::ChooseBid {
+            id: 0,
+            shipper: String::from("shipper")
+        };
+        let info33 = mock_info("buyer", &coins(1000, "LUNA"));
+        let _res = execute(deps.as_mut(), mock_env(), info33, msg33).unwrap();
+
+        let msg4 = ExecuteMsg::UploadAddress {
+            id: 0,
+            address_enc: String::from("my address buyer").into_bytes()
+        };
+        let info4 = mock_info("buyer", &coins(0, "LUNA"));
+        let _res = execute(deps.as_mut(), mock_env(), info4, msg4).unwrap();
+
+        let msg5 = ExecuteMsg::UploadAddress {
+            id: 0,
+            address_enc: String::from("my address seller").into_bytes()
+        };
+        let info5 = mock_info("seller", &coins(0, "LUNA"));
+        let _res = execute(deps.as_mut(), mock_env(), info5, msg5).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAddresses {id: 0u32, viewer: None}).unwrap();
+        let value: AddressesResponse = from_binary(&res).unwrap();
+        assert!(value.buyer.is_empty() && value.buyer_envelope.is_none());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAddresses {id: 1u32, viewer: None});
+        assert!(res.is_err());
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), ExecuteMsg::SetPermitKey {pub_key: Binary::from(b"buyer-key".to_vec())});
+        match res {
+            Err(ContractError::InvalidPublicKey {}) => {},
+            _ => panic!("Not a secp256k1 key!")
+        }
+        let outsider = viewer(deps.as_mut(), "outsider");
+        let (signing_key, pub_key) = generate_permit_key();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), ExecuteMsg::SetPermitKey {pub_key}).unwrap();
+        let wrong_key = Viewer {address: String::from("buyer"), signature: sign_permit(&generate_permit_key().0, mock_env().contract.address.as_str(), "buyer")};
+        let other_contract = Viewer {address: String::from("buyer"), signature: sign_permit(&signing_key, "other_contract", "buyer")};
+        for viewer in [outsider, Some(wrong_key), Some(other_contract)] {
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer}).unwrap();
+            let value: OrderDetailResponse = from_binary(&res).unwrap();
+            assert!(value.order.buyer_addr_enc.is_empty() && value.order.seller_addr_enc.is_empty());
+        }
+
+        for address in ["buyer", "seller", "shipper"] {
+            let viewer = viewer(deps.as_mut(), address);
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAddresses {id: 0u32, viewer}).unwrap();
+            let value: AddressesResponse = from_binary(&res).unwrap();
+            assert_eq!(String::from("my address seller").into_bytes(), value.seller);
+            assert_eq!(0, value.buyer_envelope.unwrap().version);
+        }
+    }
+
+    #[test]
+    fn test_address_envelope() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(200, "LUNA")), msg2).unwrap();
+
+        register_shipper(deps.as_mut(), "shipper");
+        let x25519_key = "-----BEGIN PUBLIC KEY-----
+MCowBQYDK2VuAyEACB/Q+L6PVhkVBHZJNyjmdQEysZ9jJIGoacw3xBcvCjM=
+-----END PUBLIC KEY-----
+";
+        let msg3 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: Some(String::from(x25519_key)),
+            algorithm: Some(EncryptionAlgorithm::RsaOaep),
+            price: coin(10, "LUNA")
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &coins(200, "LUNA")), msg3);
+        match res {
+            Err(ContractError::InvalidPublicKey {}) => {},
+            _ => panic!("Key does not match its algorithm!")
+        }
+        let msg3 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: Some(String::from(x25519_key)),
+            algorithm: Some(EncryptionAlgorithm::X25519Ecies),
+            price: coin(10, "LUNA")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &coins(200, "LUNA")), msg3).unwrap();
+        let msg4 = ExecuteMsg::ChooseBid {
+            id: 0,
+            shipper: String::from("shipper")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), msg4).unwrap();
+
+        let msg5 = ExecuteMsg::UploadAddress {
+            id: 0,
+            address_enc: String::from("my address").into_bytes()
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), msg5);
+        match res {
+            Err(ContractError::InvalidEnvelope {}) => {},
+            _ => panic!("Raw uploads are only accepted for RSA PKCS#1 v1.5 keys!")
+        }
+        let mut envelope = AddressEnvelope {
+            algorithm: EncryptionAlgorithm::X25519Ecies,
+            version: 1,
+            nonce: None,
+            ciphertext: Binary::from(b"my address")
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), ExecuteMsg::UploadAddressEnvelope {id: 0, envelope: envelope.clone()});
+        match res {
+            Err(ContractError::InvalidEnvelope {}) => {},
+            _ => panic!("X25519 envelopes need a nonce!")
+        }
+        envelope.nonce = Some(Binary::from(b"nonce"));
+        for sender in ["buyer", "seller"] {
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), ExecuteMsg::UploadAddressEnvelope {id: 0, envelope: envelope.clone()}).unwrap();
+        }
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(OrderStatus::Shipping, value.order.status);
+        assert_eq!(EncryptionAlgorithm::X25519Ecies, value.order.shipper_key_algorithm);
+        let viewer = viewer(deps.as_mut(), "seller");
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAddresses {id: 0u32, viewer}).unwrap();
+        let value: AddressesResponse = from_binary(&res).unwrap();
+        assert_eq!(Some(envelope), value.seller_envelope);
+    }
+
+    #[test]
+    fn test_reassign_shipper() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(200, "LUNA")), msg2).unwrap();
+
+        register_shipper(deps.as_mut(), "shipper1");
+        register_shipper(deps.as_mut(), "shipper2");
+        let msg3 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: None,
+            algorithm: None,
+            price: coin(10, "LUNA")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(200, "LUNA")), msg3).unwrap();
+        let msg4 = ExecuteMsg::ChooseBid {
+            id: 0,
+            shipper: String::from("shipper1")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), msg4).unwrap();
+        for sender in ["buyer", "seller"] {
+            let msg5 = ExecuteMsg::UploadAddress {
+                id: 0,
+                address_enc: String::from("my address").into_bytes()
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg5).unwrap();
+        }
+
+        let msg6 = ExecuteMsg::ReassignShipper {
+            id: 0,
+            shipper: String::from("shipper2")
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper2", &[]), msg6.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {},
+            _ => panic!("Only buyer and seller may reassign the order!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), msg6.clone()).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper2", &coins(200, "LUNA")), ExecuteMsg::AcceptReassignment {id: 0});
+        match res {
+            Err(ContractError::ReassignmentNotApproved {}) => {},
+            _ => panic!("Seller has not approved the reassignment yet!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), msg6).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper2", &coins(200, "LUNA")), ExecuteMsg::AcceptReassignment {id: 0}).unwrap();
 
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetGoods {}).unwrap();
-        let value: GoodsResponse = from_binary(&res).unwrap();
-        println!("{:?}", value);
+        let viewer = viewer(deps.as_mut(), "buyer");
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(OrderStatus::WaitingAddressUpload, value.order.status);
+        assert_eq!(Addr::unchecked("shipper2"), value.order.shipper);
+        assert!(value.order.buyer_addr_enc.is_empty() && value.order.seller_addr_enc.is_empty());
+
+        // shipper1 dropped out with the package, its collateral goes to the insurance pool
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("shipper1")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert!(value.claimable.is_empty());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::InsurancePool {}).unwrap();
+        let value: InsurancePoolResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(200, "LUNA")], value.balance);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Reassignments {id: 0u32}).unwrap();
+        let value: ReassignmentsResponse = from_binary(&res).unwrap();
+        assert_eq!(None, value.pending);
+        assert_eq!(1, value.history.len());
+        assert_eq!(Addr::unchecked("shipper1"), value.history[0].from);
+        assert_eq!(Addr::unchecked("shipper2"), value.history[0].to);
     }
 
     #[test]
-    fn test_buy() {
+    fn test_messages() {
         let mut deps = mock_dependencies(&[]);
 
         let msg = InstantiateMsg {};
-        let info = mock_info("creator", &coins(1000, "earth"));
-
-        // we can just call .unwrap() to assert this was a success
-        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        let info = mock_info("seller", &coins(1000, "earth"));
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
 
         let msg = ExecuteMsg::Post {
             name: String::from("TV"),
@@ -450,83 +4657,124 @@ mod tests {
             denom: String::from("LUNA"),
             seller_area: String::from("Montreal")
         };
-
         let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
         let msg2 = ExecuteMsg::Buy {
             name: String::from("TV"),
-            buyer_area: String::from("Montreal")
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
         };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(200, "LUNA")), msg2).unwrap();
 
-        let info2 = mock_info("buyer", &coins(2000, "LUNA"));
-        let _res = execute(deps.as_mut(), mock_env(), info2, msg2).unwrap();
+        register_shipper(deps.as_mut(), "shipper1");
+        let msg3 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: None,
+            algorithm: None,
+            price: coin(10, "LUNA")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(200, "LUNA")), msg3).unwrap();
+        let msg4 = ExecuteMsg::ChooseBid {
+            id: 0,
+            shipper: String::from("shipper1")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), msg4).unwrap();
 
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrders {}).unwrap();
-        let value: OrdersResponse = from_binary(&res).unwrap();
-        println!("{:?}", value);
+        let envelope = AddressEnvelope {
+            algorithm: EncryptionAlgorithm::RsaPkcs1v15,
+            version: 1,
+            nonce: None,
+            ciphertext: Binary::from(b"leave at the back door")
+        };
+        let to = |address: &str| MessageRecipient {address: String::from(address), envelope: envelope.clone()};
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), ExecuteMsg::SendMessage {id: 0, recipients: vec![to("shipper1"), to("seller")]});
+        match res {
+            Err(ContractError::InvalidRecipient {}) => {},
+            _ => panic!("Seller has no key registered!")
+        }
+        let res = execute(deps.as_mut(), mock_env(), mock_info("outsider", &[]), ExecuteMsg::SendMessage {id: 0, recipients: vec![to("shipper1")]});
+        match res {
+            Err(ContractError::Unauthorized {}) => {},
+            _ => panic!("Only order participants may send messages!")
+        }
+        let mut too_large = to("shipper1");
+        too_large.envelope.ciphertext = Binary::from(vec![0u8; MAX_MESSAGE_BYTES + 1]);
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), ExecuteMsg::SendMessage {id: 0, recipients: vec![too_large]});
+        match res {
+            Err(ContractError::MessageTooLarge {}) => {},
+            _ => panic!("Message exceeds the size cap!")
+        }
 
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAddresses {id: 0u32}).unwrap();
-        let value: AddressesResponse = from_binary(&res).unwrap();
-        println!("{:?}", value);
+        let msg5 = ExecuteMsg::RegisterKey {
+            pub_key: String::from(SHIPPER_KEY_2),
+            algorithm: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), msg5).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), ExecuteMsg::SendMessage {id: 0, recipients: vec![to("shipper1"), to("seller")]}).unwrap();
+        for _ in 0..3 {
+            let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &[]), ExecuteMsg::SendMessage {id: 0, recipients: vec![to("seller")]}).unwrap();
+        }
 
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32}).unwrap();
-        let value: OrderDetailResponse = from_binary(&res).unwrap();
-        println!("{:?}", value);
+        let seller = viewer(deps.as_mut(), "seller").unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Messages {id: 0, viewer: seller.clone(), start_after: None, limit: Some(2)}).unwrap();
+        let value: MessagesResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![0, 1], value.messages.iter().map(|x| x.index).collect::<Vec<_>>());
+        assert_eq!(Some(envelope.clone()), value.messages[0].envelope);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Messages {id: 0, viewer: seller, start_after: Some(1), limit: None}).unwrap();
+        let value: MessagesResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![2, 3], value.messages.iter().map(|x| x.index).collect::<Vec<_>>());
+
+        let outsider = viewer(deps.as_mut(), "outsider").unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Messages {id: 0, viewer: outsider, start_after: None, limit: None});
+        assert!(res.is_err());
     }
 
     #[test]
-    fn test_reset() {
+    fn test_key_registry() {
         let mut deps = mock_dependencies(&[]);
 
         let msg = InstantiateMsg {};
-        let info = mock_info("creator", &coins(1000, "earth"));
-
-        // we can just call .unwrap() to assert this was a success
-        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-        assert_eq!(0, res.messages.len());
-
-        let msg = ExecuteMsg::Post {
-            name: String::from("TV"),
-            price: 200,
-            denom: String::from("LUNA"),
-            seller_area: String::from("Montreal")
-        };
-        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let _res = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetGoods {}).unwrap();
-        let value: GoodsResponse = from_binary(&res).unwrap();
-        println!("{:?}", value);
-        assert_eq!(Uint128::from(200u32), value.goods[0].price.amount);
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), ExecuteMsg::RegisterKey {pub_key: String::from("rsa"), algorithm: None});
+        match res {
+            Err(ContractError::InvalidPublicKey {}) => {},
+            _ => panic!("Key is not a PEM encoded RSA key!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), ExecuteMsg::RegisterKey {pub_key: String::from(SHIPPER_KEY_1), algorithm: None}).unwrap();
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(100);
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("buyer", &[]), ExecuteMsg::RegisterKey {pub_key: String::from(SHIPPER_KEY_2), algorithm: Some(EncryptionAlgorithm::RsaOaep)}).unwrap();
 
-        let msg2 = ExecuteMsg::Reset {
-            name: String::from("TV"),
-            price: 20
-        };
-        let info2 = mock_info("creator_fake", &coins(1000, "earth"));
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetKey {address: String::from("buyer")}).unwrap();
+        let value: KeyResponse = from_binary(&res).unwrap();
+        let key = value.key.unwrap();
+        assert_eq!(String::from(SHIPPER_KEY_2), key.pub_key);
+        assert_eq!(EncryptionAlgorithm::RsaOaep, key.algorithm);
 
-        let res = execute(deps.as_mut(), mock_env(), info2, msg2.clone());
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("buyer", &[]), ExecuteMsg::RevokeKey {}).unwrap();
+        let res = execute(deps.as_mut(), env.clone(), mock_info("buyer", &[]), ExecuteMsg::RevokeKey {});
         match res {
-            Err(ContractError::Unauthorized {}) => {},
-            _ => panic!("Not buyer, not authorized!")
+            Err(ContractError::KeyNotFound {}) => {},
+            _ => panic!("Buyer has no key left to revoke!")
         }
-        let _res = execute(deps.as_mut(), mock_env(), info, msg2);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetKey {address: String::from("buyer")}).unwrap();
+        let value: KeyResponse = from_binary(&res).unwrap();
+        assert_eq!(None, value.key);
 
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetGoods {}).unwrap();
-        let value: GoodsResponse = from_binary(&res).unwrap();
-        println!("{:?}", value);
-        assert_eq!(Uint128::from(20u32), value.goods[0].price.amount);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::KeyHistory {address: String::from("buyer")}).unwrap();
+        let value: KeyHistoryResponse = from_binary(&res).unwrap();
+        assert_eq!(2, value.keys.len());
+        assert_eq!(Some(env.block.time), value.keys[0].revoked);
+        assert_eq!(Some(env.block.time), value.keys[1].revoked);
     }
 
     #[test]
-    fn test_take_order() {
+    fn test_reviews() {
         let mut deps = mock_dependencies(&[]);
 
         let msg = InstantiateMsg {};
-        let info = mock_info("creator", &coins(1000, "earth"));
-
-        // we can just call .unwrap() to assert this was a success
-        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        let info = mock_info("seller", &coins(1000, "earth"));
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
 
         let msg = ExecuteMsg::Post {
             name: String::from("TV"),
@@ -534,55 +4782,93 @@ mod tests {
             denom: String::from("LUNA"),
             seller_area: String::from("Montreal")
         };
-
         let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-
         let msg2 = ExecuteMsg::Buy {
             name: String::from("TV"),
-            buyer_area: String::from("Montreal")
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
         };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(200, "LUNA")), msg2).unwrap();
 
-        let info2 = mock_info("buyer", &coins(2000, "LUNA"));
-        let _res = execute(deps.as_mut(), mock_env(), info2, msg2).unwrap();
-
+        register_shipper(deps.as_mut(), "shipper1");
         let msg3 = ExecuteMsg::TakeOrder {
             id: 0,
-            pub_key: String::from("rsa1"),
+            pub_key: None,
+            algorithm: None,
             price: coin(10, "LUNA")
         };
-        let info3 = mock_info("shipper1", &coins(2000, "LUNA"));
-        let _res = execute(deps.as_mut(), mock_env(), info3, msg3).unwrap();
-
-        let msg4 = ExecuteMsg::TakeOrder {
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &coins(200, "LUNA")), msg3).unwrap();
+        let msg4 = ExecuteMsg::ChooseBid {
             id: 0,
-            pub_key: String::from("rsa2"),
-            price: coin(11, "LUNA")
+            shipper: String::from("shipper1")
         };
-        let info4 = mock_info("shipper2", &coins(5000, "LUNA"));
-        let _res = execute(deps.as_mut(), mock_env(), info4, msg4).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), msg4).unwrap();
+        for sender in ["buyer", "seller"] {
+            let msg5 = ExecuteMsg::UploadAddress {
+                id: 0,
+                address_enc: String::from("my address").into_bytes()
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg5).unwrap();
+        }
 
-        let msg33 = ExecuteMsg::ChooseBid {
+        let review = |subject: &str, rating: u8| ExecuteMsg::Review {
             id: 0,
-            shipper: String::from("shipper1")
+            subject: String::from(subject),
+            rating,
+            review_hash: Binary::from(sha256(b"fast and friendly"))
         };
-        let info33 = mock_info("buyer", &coins(1000, "LUNA"));
-        let _res = execute(deps.as_mut(), mock_env(), info33, msg33).unwrap();
-
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrders {}).unwrap();
-        let value_all: OrdersResponse = from_binary(&res).unwrap();
-        println!("{:?}", value_all);
-
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32}).unwrap();
-        let value_order_0: OrderDetailResponse = from_binary(&res).unwrap();
-        println!("{:?}", value_order_0);
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), review("seller", 5));
+        match res {
+            Err(ContractError::OrderNotAvailable {}) => {},
+            _ => panic!("Order is not completed yet!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), ExecuteMsg::Confirm {id: 0}).unwrap();
 
-        assert_eq!(value_all.orders[0], value_order_0.order)
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), review("seller", 5)).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), review("seller", 4));
+        match res {
+            Err(ContractError::AlreadyReviewed {}) => {},
+            _ => panic!("Buyer already reviewed the seller!")
+        }
+        for (reviewer, subject, rating) in [("buyer", "buyer", 5), ("buyer", "shipper1", 6), ("buyer", "outsider", 5)] {
+            let res = execute(deps.as_mut(), mock_env(), mock_info(reviewer, &[]), review(subject, rating));
+            match res {
+                Err(ContractError::InvalidReview {}) => {},
+                _ => panic!("Review must rate another participant from 1 to 5!")
+            }
+        }
+        let res = execute(deps.as_mut(), mock_env(), mock_info("outsider", &[]), review("seller", 5));
+        match res {
+            Err(ContractError::Unauthorized {}) => {},
+            _ => panic!("Only participants may review!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper1", &[]), review("seller", 3)).unwrap();
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(REVIEW_PERIOD + 1);
+        let res = execute(deps.as_mut(), env, mock_info("seller", &[]), review("buyer", 5));
+        match res {
+            Err(ContractError::ReviewPeriodEnded {}) => {},
+            _ => panic!("Review period is over!")
+        }
 
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetReviews {address: String::from("seller")}).unwrap();
+        let value: ReviewsResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![5, 3], value.reviews.iter().map(|x| x.rating).collect::<Vec<_>>());
+        assert_eq!(Role::Shipper, value.reviews[1].reviewer_role);
 
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetReputation {address: String::from("seller")}).unwrap();
+        let value: ReputationResponse = from_binary(&res).unwrap();
+        assert_eq!(ReputationScore {score: 63, rating: 75, reliability: 100, experience: 1, volume: 30}, value.score);
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(reputation::HALF_LIFE);
+        let res = query(deps.as_ref(), env, QueryMsg::GetReputation {address: String::from("seller")}).unwrap();
+        let value: ReputationResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::from(500u128), value.reputation.completed);
+        assert_eq!(0, value.score.experience);
     }
 
     #[test]
-    fn test_upload_address() {
+    fn test_confirm() {
         let mut deps = mock_dependencies(&[]);
 
         let msg = InstantiateMsg {};
@@ -603,15 +4889,19 @@ mod tests {
 
         let msg2 = ExecuteMsg::Buy {
             name: String::from("TV"),
-            buyer_area: String::from("Montreal")
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
         };
 
         let info2 = mock_info("buyer", &coins(2000, "LUNA"));
         let _res = execute(deps.as_mut(), mock_env(), info2, msg2).unwrap();
 
+        register_shipper(deps.as_mut(), "shipper");
+
         let msg3 = ExecuteMsg::TakeOrder {
             id: 0,
-            pub_key: String::from("rsa1"),
+            pub_key: Some(String::from(SHIPPER_KEY_1)),
+            algorithm: None,
             price: coin(10, "LUNA")
         };
         let info3 = mock_info("shipper", &coins(2000, "LUNA"));
@@ -626,27 +4916,50 @@ mod tests {
 
         let msg4 = ExecuteMsg::UploadAddress {
             id: 0,
-            address_enc: String::from("my address buyer").into_bytes()
+            address_enc: String::from("my address").into_bytes()
         };
         let info4 = mock_info("buyer", &coins(0, "LUNA"));
         let _res = execute(deps.as_mut(), mock_env(), info4, msg4).unwrap();
 
         let msg5 = ExecuteMsg::UploadAddress {
             id: 0,
-            address_enc: String::from("my address seller").into_bytes()
+            address_enc: String::from("my address").into_bytes()
         };
         let info5 = mock_info("seller", &coins(0, "LUNA"));
         let _res = execute(deps.as_mut(), mock_env(), info5, msg5).unwrap();
 
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAddresses {id: 0u32}).unwrap();
-        let value: AddressesResponse = from_binary(&res).unwrap();
-        println!("{:?}", value);
+        let msg6 = ExecuteMsg::Confirm {
+            id: 0,
+        };
+        let info6 = mock_info("buyer", &coins(0, "LUNA"));
+        let res = execute(deps.as_mut(), mock_env(), info6, msg6).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("seller")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(200, "LUNA")], value.claimable);
+        // the 1800 LUNA sent above the collateral came back with the bid, on delivery the shipper
+        // is paid its fee and gets back the 200 LUNA it bonded
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Claimable {address: String::from("shipper")}).unwrap();
+        let value: ClaimableResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(2010, "LUNA")], value.claimable);
 
+        let msg7 = ExecuteMsg::Withdraw {
+            denoms: vec![]
+        };
+        let info7 = mock_info("seller", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info7.clone(), msg7.clone()).unwrap();
+        assert_eq!(CosmosMsg::Bank(BankMsg::Send { to_address: String::from("seller"), amount: vec![coin(200, "LUNA")] }), res.messages[0].msg);
 
+        let res = execute(deps.as_mut(), mock_env(), info7, msg7);
+        match res {
+            Err(ContractError::NothingToWithdraw {}) => {},
+            _ => panic!("Balance already withdrawn!")
+        }
     }
 
     #[test]
-    fn test_confirm() {
+    fn test_prove_delivery() {
         let mut deps = mock_dependencies(&[]);
 
         let msg = InstantiateMsg {};
@@ -662,51 +4975,218 @@ mod tests {
             denom: String::from("LUNA"),
             seller_area: String::from("Montreal")
         };
-
         let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
         let msg2 = ExecuteMsg::Buy {
             name: String::from("TV"),
-            buyer_area: String::from("Montreal")
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
         };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(2000, "LUNA")), msg2).unwrap();
 
-        let info2 = mock_info("buyer", &coins(2000, "LUNA"));
-        let _res = execute(deps.as_mut(), mock_env(), info2, msg2).unwrap();
+        let msg3 = ExecuteMsg::SetDeliveryLock {
+            id: 0,
+            hash: Binary::from(sha256(b"open sesame"))
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), msg3).unwrap();
 
-        let msg3 = ExecuteMsg::TakeOrder {
+        register_shipper(deps.as_mut(), "shipper");
+        let msg4 = ExecuteMsg::TakeOrder {
             id: 0,
-            pub_key: String::from("rsa1"),
+            pub_key: None,
+            algorithm: None,
             price: coin(10, "LUNA")
         };
-        let info3 = mock_info("shipper", &coins(2000, "LUNA"));
-        let _res = execute(deps.as_mut(), mock_env(), info3, msg3).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &coins(200, "LUNA")), msg4).unwrap();
+        let msg5 = ExecuteMsg::ChooseBid {
+            id: 0,
+            shipper: String::from("shipper")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), msg5).unwrap();
+        for sender in ["buyer", "seller"] {
+            let msg6 = ExecuteMsg::UploadAddress {
+                id: 0,
+                address_enc: String::from("my address").into_bytes()
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg6).unwrap();
+        }
 
-        let msg33 = ExecuteMsg::ChooseBid {
+        let msg7 = ExecuteMsg::ProveDelivery {
+            id: 0,
+            preimage: Binary::from(b"open says me".to_vec())
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &[]), msg7);
+        match res {
+            Err(ContractError::InvalidPreimage {}) => {},
+            _ => panic!("Wrong delivery code!")
+        }
+        let msg8 = ExecuteMsg::ProveDelivery {
+            id: 0,
+            preimage: Binary::from(b"open sesame".to_vec())
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &[]), msg8).unwrap();
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), ExecuteMsg::DisputeBroken {id: 0, items: vec![]});
+        match res {
+            Err(ContractError::OrderNotAvailable {}) => {},
+            _ => panic!("Delivered orders cannot be disputed!")
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(Confirmed, value.order.status);
+        assert_eq!(Sold, value.order.goods.status);
+    }
+
+    #[test]
+    fn test_tracking() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(2000, "LUNA")), msg2).unwrap();
+
+        register_shipper(deps.as_mut(), "shipper");
+        let msg3 = ExecuteMsg::TakeOrder {
+            id: 0,
+            pub_key: None,
+            algorithm: None,
+            price: coin(10, "LUNA")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &coins(200, "LUNA")), msg3).unwrap();
+        let msg4 = ExecuteMsg::ChooseBid {
             id: 0,
             shipper: String::from("shipper")
         };
-        let info33 = mock_info("buyer", &coins(1000, "LUNA"));
-        let _res = execute(deps.as_mut(), mock_env(), info33, msg33).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), msg4).unwrap();
+        for sender in ["buyer", "seller"] {
+            let msg5 = ExecuteMsg::UploadAddress {
+                id: 0,
+                address_enc: String::from("my address").into_bytes()
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg5).unwrap();
+        }
 
-        let msg4 = ExecuteMsg::UploadAddress {
+        let msg_checkpoint = ExecuteMsg::PostCheckpoint {
             id: 0,
-            address_enc: String::from("my address").into_bytes()
+            kind: CheckpointKind::InTransit { area: String::from("Laval") },
+            note_enc: None
         };
-        let info4 = mock_info("buyer", &coins(0, "LUNA"));
-        let _res = execute(deps.as_mut(), mock_env(), info4, msg4).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), msg_checkpoint.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {},
+            _ => panic!("Only the shipper posts checkpoints!")
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &[]), ExecuteMsg::PostCheckpoint {id: 0, kind: CheckpointKind::PickedUp, note_enc: None}).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &[]), msg_checkpoint).unwrap();
 
-        let msg5 = ExecuteMsg::UploadAddress {
+        for address in ["buyer", "seller", "shipper"] {
+            let viewer = viewer(deps.as_mut(), address).unwrap();
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::Tracking {id: 0u32, viewer}).unwrap();
+            let value: TrackingResponse = from_binary(&res).unwrap();
+            assert_eq!(vec![CheckpointKind::PickedUp, CheckpointKind::InTransit { area: String::from("Laval") }], value.checkpoints.into_iter().map(|x| x.kind).collect::<Vec<_>>());
+        }
+
+        let outsider = viewer(deps.as_mut(), "outsider").unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Tracking {id: 0u32, viewer: outsider});
+        assert!(res.is_err());
+        let forged = Viewer {address: String::from("buyer"), signature: sign_permit(&generate_permit_key().0, mock_env().contract.address.as_str(), "buyer")};
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Tracking {id: 0u32, viewer: forged});
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_dispute_before_delivery_proof() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {};
+        let info = mock_info("seller", &coins(1000, "earth"));
+        let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Post {
+            name: String::from("TV"),
+            price: 200,
+            denom: String::from("LUNA"),
+            seller_area: String::from("Montreal")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(200, "LUNA")), ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: Some(Binary::from(b"not a hash".to_vec()))
+        });
+        match res {
+            Err(ContractError::InvalidPreimage {}) => {},
+            _ => panic!("Delivery lock must be a sha256 hash!")
+        }
+        let msg2 = ExecuteMsg::Buy {
+            name: String::from("TV"),
+            buyer_area: String::from("Montreal"),
+            delivery_lock: Some(Binary::from(sha256(b"open sesame")))
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(200, "LUNA")), msg2).unwrap();
+
+        register_shipper(deps.as_mut(), "shipper");
+        let msg3 = ExecuteMsg::TakeOrder {
             id: 0,
-            address_enc: String::from("my address").into_bytes()
+            pub_key: None,
+            algorithm: None,
+            price: coin(10, "LUNA")
         };
-        let info5 = mock_info("seller", &coins(0, "LUNA"));
-        let _res = execute(deps.as_mut(), mock_env(), info5, msg5).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("shipper", &coins(200, "LUNA")), msg3).unwrap();
+        let msg4 = ExecuteMsg::ChooseBid {
+            id: 0,
+            shipper: String::from("shipper")
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &coins(20, "LUNA")), msg4).unwrap();
+        for sender in ["buyer", "seller"] {
+            let msg5 = ExecuteMsg::UploadAddress {
+                id: 0,
+                address_enc: String::from("my address").into_bytes()
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg5).unwrap();
+        }
 
-        let msg6 = ExecuteMsg::Confirm {
+        // the buyer disputes before the shipper had a chance to prove delivery
+        for msg6 in [ExecuteMsg::DisputeBroken {id: 0, items: vec![]}, ExecuteMsg::DisputeUnsatisfied {id: 0, items: vec![]}] {
+            let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), msg6);
+            match res {
+                Err(ContractError::DeliveryLocked {}) => {},
+                _ => panic!("Shipper can still prove delivery!")
+            }
+        }
+
+        // without a proof by the deadline the dispute goes through and the lock no longer settles the order
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(DELIVERY_PROOF_PERIOD);
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("buyer", &[]), ExecuteMsg::DisputeBroken {id: 0, items: vec![]}).unwrap();
+        let msg7 = ExecuteMsg::ProveDelivery {
             id: 0,
+            preimage: Binary::from(b"open sesame".to_vec())
         };
-        let info6 = mock_info("buyer", &coins(0, "LUNA"));
-        let _res = execute(deps.as_mut(), mock_env(), info6, msg6).unwrap();
+        let res = execute(deps.as_mut(), env, mock_info("shipper", &[]), msg7);
+        match res {
+            Err(ContractError::OrderNotAvailable {}) => {},
+            _ => panic!("Order is disputed!")
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderDetail {id: 0u32, viewer: None}).unwrap();
+        let value: OrderDetailResponse = from_binary(&res).unwrap();
+        assert_eq!(DisputingBroken, value.order.status);
     }
 
     #[test]
@@ -731,23 +5211,23 @@ mod tests {
 
         let msg2 = ExecuteMsg::Buy {
             name: String::from("TV"),
-            buyer_area: String::from("Montreal")
+            buyer_area: String::from("Montreal"),
+            delivery_lock: None
         };
 
         let info2 = mock_info("buyer", &coins(2000, "LUNA"));
         let _res = execute(deps.as_mut(), mock_env(), info2, msg2).unwrap();
 
-        let mut rng = OsRng;
-        let bits = 1024;
-        let private_key1 = RsaPrivateKey::new(&mut rng, bits).expect("failed to generate a key");
-        let public_key1 = RsaPublicKey::from(&private_key1);
+        let (private_key1, public_key1) = generate_rsa_key(2048).expect("failed to generate a key");
+        let (_, public_key2) = generate_rsa_key(2048).expect("failed to generate a key");
 
-        let private_key2 = RsaPrivateKey::new(&mut rng, bits).expect("failed to generate a key");
-        let public_key2 = RsaPublicKey::from(&private_key2);
+        register_shipper(deps.as_mut(), "shipper1");
+        register_shipper(deps.as_mut(), "shipper2");
 
         let msg31 = ExecuteMsg::TakeOrder {
             id: 0,
-            pub_key: public_key1.clone().to_public_key_pem().unwrap(),
+            pub_key: Some(public_key1),
+            algorithm: None,
             price: coin(10, "LUNA")
         };
         let info31 = mock_info("shipper1", &coins(2000, "LUNA"));
@@ -755,7 +5235,8 @@ mod tests {
 
         let msg32 = ExecuteMsg::TakeOrder {
             id: 0,
-            pub_key: public_key2.clone().to_public_key_pem().unwrap(),
+            pub_key: Some(public_key2),
+            algorithm: Some(EncryptionAlgorithm::RsaOaep),
             price: coin(8, "LUNA")
         };
         let info32 = mock_info("shipper2", &coins(2000, "LUNA"));
@@ -770,14 +5251,14 @@ mod tests {
 
         let buyer_address = "Ottawa University";
         let msg_query = QueryMsg::GetOrderDetail {
-            id: 0
+            id: 0,
+            viewer: None
         };
         let res = query(deps.as_ref(), mock_env(),msg_query).unwrap();
         let order: OrderDetailResponse = from_binary(&res).unwrap();
         let shipper_key = order.order.shipper_key;
 
-        let pub_key = RsaPublicKey::from_public_key_pem(&shipper_key).unwrap();
-        let buyer_addr_enc= pub_key.encrypt(&mut rng, PaddingScheme::new_pkcs1v15_encrypt(), buyer_address.as_bytes()).expect("failed to encrypt");
+        let buyer_addr_enc = encrypt_address_legacy(&shipper_key, buyer_address).expect("failed to encrypt");
         let msg4 = ExecuteMsg::UploadAddress {
             id: 0,
             address_enc: buyer_addr_enc
@@ -786,34 +5267,31 @@ mod tests {
         let _res = execute(deps.as_mut(), mock_env(), info4, msg4).unwrap();
 
         let seller_address = "McGill University";
-        let seller_addr_enc= pub_key.encrypt(&mut rng, PaddingScheme::new_pkcs1v15_encrypt(), seller_address.as_bytes()).expect("failed to encrypt");
+        let seller_addr_enc = encrypt_address(&shipper_key, order.order.shipper_key_algorithm, seller_address).expect("failed to encrypt");
 
-        let msg5 = ExecuteMsg::UploadAddress {
+        let msg5 = ExecuteMsg::UploadAddressEnvelope {
             id: 0,
-            address_enc: seller_addr_enc
+            envelope: seller_addr_enc
         };
         let info5 = mock_info("seller", &coins(0, "LUNA"));
         let _res = execute(deps.as_mut(), mock_env(), info5, msg5).unwrap();
 
-        let msg_query = QueryMsg::GetOrderDetail {
-            id: 0
+        let msg_query = QueryMsg::GetAddresses {
+            id: 0,
+            viewer: viewer(deps.as_mut(), "shipper1")
         };
         let res = query(deps.as_ref(), mock_env(),msg_query).unwrap();
-        let order: OrderDetailResponse = from_binary(&res).unwrap();
-
-        let buyer_addr = private_key1.decrypt(PaddingScheme::new_pkcs1v15_encrypt(), &order.order.buyer_addr_enc).expect("failed to decrypt");
-        let seller_addr = private_key1.decrypt(PaddingScheme::new_pkcs1v15_encrypt(), &order.order.seller_addr_enc).expect("failed to decrypt");
+        let addresses: AddressesResponse = from_binary(&res).unwrap();
 
-        let mut buyer_addr_dec = String::from_utf8(buyer_addr).unwrap();
-        let mut seller_addr_dec = String::from_utf8(seller_addr).unwrap();
-        buyer_addr_dec.truncate(buyer_address.len());
-        seller_addr_dec.truncate(seller_address.len());
+        let buyer_addr_dec = decrypt_address(&private_key1, &addresses.buyer_envelope.unwrap()).expect("failed to decrypt");
+        let seller_addr_dec = decrypt_address(&private_key1, &addresses.seller_envelope.unwrap()).expect("failed to decrypt");
 
         assert_eq!(buyer_addr_dec, buyer_address);
         assert_eq!(seller_addr_dec, seller_address);
 
         let msg6 = ExecuteMsg::DisputeUnsatisfied {
             id: 0,
+            items: vec![]
         };
         let info6 = mock_info("buyer", &coins(0, "LUNA"));
         let _res = execute(deps.as_mut(), mock_env(), info6, msg6).unwrap();
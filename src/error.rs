@@ -19,7 +19,154 @@ pub enum ContractError {
     OrderNotAvailable {},
 
     #[error("ShipperNotFound")]
-    ShipperNotFound {}
+    ShipperNotFound {},
+
+    #[error("NothingToWithdraw")]
+    NothingToWithdraw {},
+
+    #[error("WrongListingType")]
+    WrongListingType {},
+
+    #[error("InvalidEndTime")]
+    InvalidEndTime {},
+
+    #[error("AuctionEnded")]
+    AuctionEnded {},
+
+    #[error("AuctionNotEnded")]
+    AuctionNotEnded {},
+
+    #[error("BidTooLow")]
+    BidTooLow {},
+
+    #[error("InvalidPrice")]
+    InvalidPrice {},
+
+    #[error("OfferNotAvailable")]
+    OfferNotAvailable {},
+
+    #[error("OfferExpired")]
+    OfferExpired {},
+
+    #[error("WrongBiddingMode")]
+    WrongBiddingMode {},
+
+    #[error("CommitPeriodEnded")]
+    CommitPeriodEnded {},
+
+    #[error("NotInRevealPeriod")]
+    NotInRevealPeriod {},
+
+    #[error("RevealPeriodNotEnded")]
+    RevealPeriodNotEnded {},
+
+    #[error("CommitmentMismatch")]
+    CommitmentMismatch {},
+
+    #[error("BiddingNotEnded")]
+    BiddingNotEnded {},
+
+    #[error("BidsAvailable")]
+    BidsAvailable {},
+
+    #[error("ShipperNotRegistered")]
+    ShipperNotRegistered {},
+
+    #[error("AreaNotServed")]
+    AreaNotServed {},
+
+    #[error("ShipperAtCapacity")]
+    ShipperAtCapacity {},
+
+    #[error("ShipperBusy")]
+    ShipperBusy {},
+
+    #[error("InsufficientStake")]
+    InsufficientStake {},
+
+    #[error("InvalidLocation")]
+    InvalidLocation {},
+
+    #[error("OutOfRange")]
+    OutOfRange {},
+
+    #[error("InvalidRoute")]
+    InvalidRoute {},
+
+    #[error("NoDeliveryLock")]
+    NoDeliveryLock {},
+
+    #[error("InvalidPreimage")]
+    InvalidPreimage {},
+
+    #[error("DeliveryLocked")]
+    DeliveryLocked {},
+
+    #[error("TooManyCheckpoints")]
+    TooManyCheckpoints {},
+
+    #[error("InvalidConfig")]
+    InvalidConfig {},
+
+    #[error("InvalidPublicKey")]
+    InvalidPublicKey {},
+
+    #[error("InvalidEnvelope")]
+    InvalidEnvelope {},
+
+    #[error("ReassignmentNotApproved")]
+    ReassignmentNotApproved {},
+
+    #[error("InvalidRecipient")]
+    InvalidRecipient {},
+
+    #[error("MessageTooLarge")]
+    MessageTooLarge {},
+
+    #[error("TooManyMessages")]
+    TooManyMessages {},
+
+    #[error("KeyNotFound")]
+    KeyNotFound {},
+
+    #[error("InvalidReview")]
+    InvalidReview {},
+
+    #[error("ReviewPeriodEnded")]
+    ReviewPeriodEnded {},
+
+    #[error("AlreadyReviewed")]
+    AlreadyReviewed {},
+
+    #[error("InvalidStore")]
+    InvalidStore {},
+
+    #[error("DenomNotAccepted")]
+    DenomNotAccepted {},
+
+    #[error("UnknownCategory")]
+    UnknownCategory {},
+
+    #[error("InvalidMetadata")]
+    InvalidMetadata {},
+
+    #[error("InsufficientStock")]
+    InsufficientStock {},
+
+    #[error("InvalidCart")]
+    InvalidCart {},
+
+    #[error("InvalidBatchSize")]
+    InvalidBatchSize {},
+
+    #[error("ContestPeriodEnded")]
+    ContestPeriodEnded {},
+
+    #[error("ContestPeriodNotEnded")]
+    ContestPeriodNotEnded {},
+
+    #[error("DisputeContested")]
+    DisputeContested {}
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
@@ -1,5 +1,11 @@
-use cosmwasm_std::{Coin, coin, Uint128};
+use cosmwasm_std::{Addr, Coin, coin, StdResult, Storage, Timestamp, Uint128};
+use std::convert::TryFrom;
+use std::str::FromStr;
+use pkcs8::pkcs1::RsaPublicKey;
+use pkcs8::{ObjectIdentifier, PublicKeyDocument};
+use sha2::{Digest, Sha256};
 use crate::ContractError;
+use crate::state::{AddressEnvelope, DutchAuction, EncryptionAlgorithm, Location, CLAIMABLE};
 
 
 // Acknowledgement: cw-nameservice = 0.10.0
@@ -25,6 +31,54 @@ pub fn assert_sent_sufficient_coin(
     Ok(())
 }
 
+// Smallest RSA modulus accepted for address encryption keys
+pub const MIN_RSA_KEY_BITS: usize = 2048;
+
+// Envelope version written by current clients, version 0 marks a legacy raw upload
+pub const ADDRESS_ENVELOPE_VERSION: u32 = 1;
+
+const RSA_OID: ObjectIdentifier = ObjectIdentifier::new("1.2.840.113549.1.1.1");
+const X25519_OID: ObjectIdentifier = ObjectIdentifier::new("1.3.101.110");
+
+// Shipper keys must be PEM encoded SubjectPublicKeyInfo matching their algorithm, RSA keys need
+// at least MIN_RSA_KEY_BITS, otherwise buyer and seller can not upload their addresses.
+pub fn assert_valid_pub_key(pub_key: &str, algorithm: EncryptionAlgorithm) -> Result<(), ContractError> {
+    let doc = PublicKeyDocument::from_str(pub_key).map_err(|_| ContractError::InvalidPublicKey {})?;
+    let spki = doc.spki();
+    match algorithm {
+        EncryptionAlgorithm::RsaPkcs1v15 | EncryptionAlgorithm::RsaOaep => {
+            if spki.algorithm.oid != RSA_OID {
+                return Err(ContractError::InvalidPublicKey {});
+            }
+            let key = RsaPublicKey::try_from(spki.subject_public_key).map_err(|_| ContractError::InvalidPublicKey {})?;
+            let modulus = key.modulus.as_bytes();
+            let bits = match modulus.first() {
+                Some(msb) => modulus.len() * 8 - msb.leading_zeros() as usize,
+                None => 0
+            };
+            if bits < MIN_RSA_KEY_BITS {
+                return Err(ContractError::InvalidPublicKey {});
+            }
+        }
+        EncryptionAlgorithm::X25519Ecies => {
+            if spki.algorithm.oid != X25519_OID || spki.subject_public_key.len() != 32 {
+                return Err(ContractError::InvalidPublicKey {});
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn assert_valid_envelope(envelope: &AddressEnvelope, algorithm: EncryptionAlgorithm) -> Result<(), ContractError> {
+    if envelope.algorithm != algorithm || envelope.version != ADDRESS_ENVELOPE_VERSION || envelope.ciphertext.is_empty() {
+        return Err(ContractError::InvalidEnvelope {});
+    }
+    if algorithm == EncryptionAlgorithm::X25519Ecies && envelope.nonce.is_none() {
+        return Err(ContractError::InvalidEnvelope {});
+    }
+    Ok(())
+}
+
 pub fn merge_coin(coin1: Vec<Coin>, coin2: Vec<Coin>) -> Vec<Coin> {
     let mut merged_coin = vec![];
     for cc in coin1.iter() {
@@ -36,4 +90,92 @@ pub fn merge_coin(coin1: Vec<Coin>, coin2: Vec<Coin>) -> Vec<Coin> {
         merged_coin.push(coin(num.u128(), cc.clone().denom));
     }
     merged_coin
-}
\ No newline at end of file
+}
+// Settlements never send funds directly; they credit the recipient's claimable balance
+// so that one failing recipient cannot revert the whole settlement.
+pub fn credit_claimable(storage: &mut dyn Storage, recipient: &Addr, amount: &[Coin]) -> StdResult<()> {
+    for c in amount.iter().filter(|c| !c.amount.is_zero()) {
+        CLAIMABLE.update(storage, (recipient, &c.denom), |balance| -> StdResult<Uint128> {
+            Ok(balance.unwrap_or_default().checked_add(c.amount)?)
+        })?;
+    }
+    Ok(())
+}
+
+pub fn dutch_auction_price(auction: &DutchAuction, now: Timestamp) -> Uint128 {
+    if now <= auction.start_time {
+        return auction.start_price;
+    }
+    if now >= auction.end_time {
+        return auction.floor_price;
+    }
+    let elapsed = now.seconds() - auction.start_time.seconds();
+    let duration = auction.end_time.seconds() - auction.start_time.seconds();
+    let decay = (auction.start_price - auction.floor_price).multiply_ratio(elapsed, duration);
+    auction.start_price - decay
+}
+
+// The shipper address is part of the preimage so a commitment cannot be copied by another shipper.
+// The algorithm is named as in the JSON messages, e.g. rsa_pkcs1v15.
+pub fn bid_commitment(shipper: &Addr, price: &Coin, salt: &str, pub_key: &str, algorithm: EncryptionAlgorithm) -> Vec<u8> {
+    let algorithm = match algorithm {
+        EncryptionAlgorithm::RsaPkcs1v15 => "rsa_pkcs1v15",
+        EncryptionAlgorithm::RsaOaep => "rsa_oaep",
+        EncryptionAlgorithm::X25519Ecies => "x25519_ecies"
+    };
+    let preimage = format!("{}|{}|{}|{}|{}", shipper, price, salt, pub_key, algorithm);
+    sha256(preimage.as_bytes())
+}
+
+pub fn sha256(data: &[u8]) -> Vec<u8> {
+    Sha256::digest(data).to_vec()
+}
+
+// What a viewer signs to read the private parts of this contract's orders
+pub fn permit_message(contract: &str, address: &str) -> Vec<u8> {
+    format!("permit:{}:{}", contract, address).into_bytes()
+}
+
+// Compressed (33 bytes) or uncompressed (65 bytes) secp256k1 public key
+pub fn is_valid_permit_key(pub_key: &[u8]) -> bool {
+    matches!((pub_key.len(), pub_key.first()), (33, Some(0x02)) | (33, Some(0x03)) | (65, Some(0x04)))
+}
+
+pub fn is_valid_location(location: &Location) -> bool {
+    location.latitude.abs() <= 90_000_000 && location.longitude.abs() <= 180_000_000
+}
+
+// Equirectangular distance in meters. Wasm contracts cannot use floats, so the cosine of the
+// mean latitude comes from Bhaskara's approximation cos(d) ~ (32400 - 4d^2) / (32400 + d^2),
+// which stays within 0.2% of the exact value.
+pub fn distance_m(from: &Location, to: &Location) -> u64 {
+    const METERS_PER_DEGREE: i128 = 111_195;
+    const MICRO: i128 = 1_000_000;
+    let dlat = (to.latitude as i128) - (from.latitude as i128);
+    let mut dlon = (to.longitude as i128) - (from.longitude as i128);
+    if dlon > 180 * MICRO {
+        dlon -= 360 * MICRO;
+    } else if dlon < -180 * MICRO {
+        dlon += 360 * MICRO;
+    }
+    let mean_lat = ((from.latitude as i128) + (to.latitude as i128)) / 2;
+    let d2 = mean_lat * mean_lat;
+    let cos_num = 32_400 * MICRO * MICRO - 4 * d2;
+    let cos_den = 32_400 * MICRO * MICRO + d2;
+    let dy = dlat * METERS_PER_DEGREE / MICRO;
+    let dx = dlon * METERS_PER_DEGREE * cos_num / cos_den / MICRO;
+    isqrt((dx * dx + dy * dy) as u128) as u64
+}
+
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = n;
+    let mut y = x.div_ceil(2);
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
@@ -1,3 +1,4 @@
+#![allow(non_snake_case)]
 extern crate core;
 
 pub mod contract;
@@ -5,6 +6,9 @@ mod error;
 pub mod msg;
 pub mod state;
 pub mod helper;
+pub mod reputation;
+#[cfg(any(test, feature = "client"))]
+pub mod client;
 
 
 pub use crate::error::ContractError;
@@ -1,7 +1,8 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use cosmwasm_std::{Coin, Uint128};
-use crate::state::{Goods, Order};
+use cosmwasm_std::{Addr, Binary, Coin, Timestamp};
+use crate::reputation::{Reputation, ReputationScore};
+use crate::state::{AddressEnvelope, Checkpoint, CheckpointKind, EncryptionAlgorithm, Goods, Location, Offer, Order, Reassignment, ReassignmentProposal, ReturnPolicy, Review, SelectionPolicy, Shipper, Store, UserKey};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
@@ -10,26 +11,109 @@ pub struct InstantiateMsg {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
+    // fields left as None keep their current value, a zero min_shipper_stake removes the minimum
+    UpdateConfig {min_shipper_stake: Option<Coin>, collateral_bps: Option<u64>, insurance_bps: Option<u64>, arbiter: Option<String>, max_batch_size: Option<u32>},
+    // secp256k1 key the sender signs query permits with, registering a new one revokes older permits
+    SetPermitKey {pub_key: Binary},
+    AddCategory {category: String},
+    // listings already in the category keep it
+    RemoveCategory {category: String},
+    FundInsurance {},
+    // creates or updates the sender's store, payout_address defaults to the sender
+    SetStore {name: String, description_hash: Binary, default_area: String, return_policy: ReturnPolicy, payout_address: Option<String>, accepted_denoms: Vec<String>, #[serde(default)] shipping_subsidy: Option<Coin>},
     Post {name: String, price: u32, denom: String, seller_area: String},
-    Buy {name: String, buyer_area: String},
+    PostAuction {name: String, reserve_price: u32, min_increment: u32, denom: String, seller_area: String, end_time: u64},
+    PostDutchAuction {name: String, start_price: u32, floor_price: u32, denom: String, seller_area: String, duration: u64},
+    Bid {name: String, buyer_area: String},
+    Finalize {name: String},
+    // delivery_lock is the sha256 of the secret the buyer hands the courier on delivery
+    Buy {name: String, buyer_area: String, #[serde(default)] delivery_lock: Option<Binary>},
+    // one order for several fixed-price goods of the same seller
+    Checkout {items: Vec<CartItem>, buyer_area: String, #[serde(default)] delivery_lock: Option<Binary>},
     Reset { name: String, price: u32 },
-    TakeOrder {id: u32, pub_key: String, price: Coin},
+    // batches apply every item or none of them
+    BatchPost {items: Vec<PostItem>},
+    BatchReset {items: Vec<ResetItem>},
+    SetGoodsLocation {name: String, location: Location},
+    SetGoodsStock {name: String, stock: u32},
+    // tags are lowercased and deduplicated
+    SetGoodsMetadata {name: String, category: Option<String>, tags: Vec<String>, description_hash: Option<Binary>, image_uris: Vec<String>},
+    SetBuyerLocation {id: u32, location: Location},
+    MakeOffer {name: String, buyer_area: String, expires_in: u64},
+    AcceptOffer {name: String, offer_id: u32},
+    RejectOffer {name: String, offer_id: u32},
+    CounterOffer {name: String, offer_id: u32, price: u32, expires_in: u64},
+    AcceptCounterOffer {name: String, offer_id: u32},
+    CancelOffer {name: String, offer_id: u32},
+    RegisterShipper {service_areas: Vec<String>, pub_key: String, key_algorithm: Option<EncryptionAlgorithm>, capacity: u32, rate_per_km: Option<Coin>, max_radius_km: Option<u32>},
+    UnregisterShipper {},
+    TakeOrder {id: u32, pub_key: Option<String>, algorithm: Option<EncryptionAlgorithm>, price: Coin},
+    // the funds sent must cover the collateral of every bid
+    BatchTakeOrder {items: Vec<TakeOrderItem>},
+    SealBidding {id: u32, commit_period: u64, reveal_period: u64, deposit: Coin},
+    UnsealBidding {id: u32},
+    CommitBid {id: u32, commitment: Binary},
+    RevealBid {id: u32, pub_key: String, algorithm: Option<EncryptionAlgorithm>, price: Coin, salt: String},
     ChooseBid {id: u32, shipper: String},
-    UploadAddress {id: u32, address_enc: Vec<u8>},
+    ChooseRoute {id: u32, shippers: Vec<String>},
+    ConfirmHandoff {id: u32},
+    ReportHandoffDamage {id: u32},
+    ContestDamage {id: u32},
+    ResolveDamage {id: u32, upheld: bool},
+    AutoSelect {id: u32, bidding_period: u64, policy: SelectionPolicy, max_fee: Coin},
+    SelectBid {id: u32},
+    // refunds the auto-select escrow when no bid can be selected
+    CancelAutoSelect {id: u32},
+    UploadAddress {id: u32, address_enc: Vec<u8>},  // legacy RSA PKCS#1 v1.5 upload
+    UploadAddressEnvelope {id: u32, envelope: AddressEnvelope},
+    ReassignShipper {id: u32, shipper: String},
+    AcceptReassignment {id: u32},
+    RegisterKey {pub_key: String, algorithm: Option<EncryptionAlgorithm>},
+    RevokeKey {},
+    SendMessage {id: u32, recipients: Vec<MessageRecipient>},
+    PostCheckpoint {id: u32, kind: CheckpointKind, note_enc: Option<Binary>},
+    SetDeliveryLock {id: u32, hash: Binary},
+    ProveDelivery {id: u32, preimage: Binary},
     Confirm {id: u32},
-    DisputeBroken {id: u32},
-    DisputeUnsatisfied {id: u32},
-    DisputeConfirm {id: u32}
+    // items of a cart order, all of them when empty
+    DisputeBroken {id: u32, #[serde(default)] items: Vec<CartItem>},
+    DisputeUnsatisfied {id: u32, #[serde(default)] items: Vec<CartItem>},
+    DisputeConfirm {id: u32},
+    Review {id: u32, subject: String, rating: u8, review_hash: Binary},
+    Withdraw {denoms: Vec<String>}
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     GetGoods {},
+    GetPrice {name: String},
+    GetOffers {name: String},
+    GetStore {owner: String},
+    Categories {},
+    GoodsByCategory {category: String, start_after: Option<String>, limit: Option<u32>},
+    GoodsByTag {tag: String, start_after: Option<String>, limit: Option<u32>},
+    // goods of the store ordered by name
+    StoreListings {owner: String, start_after: Option<String>, limit: Option<u32>},
     GetOrders {},
-    GetOrderDetail {id: u32},
-    GetAddresses {id: u32},
-    GetBalance {}
+    // without a valid viewer the encrypted addresses are redacted
+    GetOrderDetail {id: u32, viewer: Option<Viewer>},
+    GetAddresses {id: u32, viewer: Option<Viewer>},
+    // checkpoints are only shown to the participants of the order
+    Tracking {id: u32, viewer: Viewer},
+    Reassignments {id: u32},
+    // messages the viewer sent or received, oldest first
+    Messages {id: u32, viewer: Viewer, start_after: Option<u32>, limit: Option<u32>},
+    GetBalance {},
+    Claimable {address: String},
+    InsurancePool {},
+    GetReviews {address: String},
+    GetReputation {address: String},
+    GetKey {address: String},
+    KeyHistory {address: String},
+    GetShipper {address: String},
+    GetShippers {area: String},
+    GetShippingEstimates {id: u32}
 }
 
 // We define a custom struct for each query response
@@ -38,23 +122,166 @@ pub struct GoodsResponse {
     pub goods: Vec<Goods>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PostItem {
+    pub name: String,
+    pub price: u32,
+    pub denom: String,
+    pub seller_area: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ResetItem {
+    pub name: String,
+    pub price: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TakeOrderItem {
+    pub id: u32,
+    pub pub_key: Option<String>,
+    pub algorithm: Option<EncryptionAlgorithm>,
+    pub price: Coin,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CartItem {
+    pub name: String,
+    pub quantity: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CategoriesResponse {
+    pub categories: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StoreResponse {
+    pub store: Store,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceResponse {
+    pub price: Coin,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OffersResponse {
+    pub offers: Vec<Offer>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct OrdersResponse {
     pub orders: Vec<Order>,
 }
 
+// The message encrypted for one recipient's key
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MessageRecipient {
+    pub address: String,
+    pub envelope: AddressEnvelope,
+}
+
+// Credentials for private queries: a signature over helper::permit_message by the key the
+// address registered with SetPermitKey. Nothing secret is stored on chain, but a permit works for
+// anyone who holds it until the key is replaced.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Viewer {
+    pub address: String,
+    pub signature: Binary,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct OrderDetailResponse {
     pub order: Order,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InsurancePoolResponse {
+    pub balance: Vec<Coin>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ShipperResponse {
+    pub shipper: Shipper,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ShippersResponse {
+    pub shippers: Vec<Shipper>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ShippingEstimate {
+    pub shipper: Addr,
+    pub fee: Coin,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ShippingEstimatesResponse {
+    pub distance_m: u64,
+    pub estimates: Vec<ShippingEstimate>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReassignmentsResponse {
+    pub pending: Option<ReassignmentProposal>,
+    pub history: Vec<Reassignment>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReviewsResponse {
+    pub reviews: Vec<Review>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReputationResponse {
+    pub reputation: Reputation,
+    pub score: ReputationScore,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct KeyResponse {
+    pub key: Option<UserKey>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct KeyHistoryResponse {
+    pub keys: Vec<UserKey>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MessageView {
+    pub index: u32,
+    pub sender: Addr,
+    pub time: Timestamp,
+    pub envelope: Option<AddressEnvelope>,  // None on messages the viewer sent to others
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MessagesResponse {
+    pub messages: Vec<MessageView>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TrackingResponse {
+    pub checkpoints: Vec<Checkpoint>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AddressesResponse {
     pub buyer: Vec<u8>,
     pub seller: Vec<u8>,
+    pub buyer_envelope: Option<AddressEnvelope>,
+    pub seller_envelope: Option<AddressEnvelope>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct BalanceResponse {
     pub balance: Vec<Coin>
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimableResponse {
+    pub claimable: Vec<Coin>
+}
@@ -0,0 +1,172 @@
+use cosmwasm_std::{Addr, Coin, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::Map;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Every component loses half its weight after this many seconds
+pub const HALF_LIFE: u64 = 180 * 24 * 3600;
+// Counters are kept in thousandths so that decay does not round them away
+const SCALE: u128 = 1000;
+
+// Decayed reputation counters of one address, as of `updated`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct Reputation {
+    pub rating_sum: Uint128,
+    pub rating_count: Uint128,
+    pub completed: Uint128,
+    pub disputes_won: Uint128,
+    pub disputes_lost: Uint128,
+    pub volume: Vec<Coin>,  // one entry per denom, amounts of different denoms are never added up
+    pub updated: Timestamp
+}
+
+// Components on a 0 to 100 scale, `score` weighs them 40/30/20/10
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReputationScore {
+    pub score: u64,
+    pub rating: u64,
+    pub reliability: u64,
+    pub experience: u64,
+    pub volume: u64
+}
+
+pub enum ReputationEvent {
+    Rated(u8),
+    Completed(Coin),  // traded or shipped amount
+    DisputeWon,
+    DisputeLost
+}
+
+pub const REPUTATION: Map<&Addr, Reputation> = Map::new("reputation");
+
+pub fn record(storage: &mut dyn Storage, address: &Addr, now: Timestamp, event: ReputationEvent) -> StdResult<()> {
+    let mut reputation = load(storage, address, now)?;
+    match event {
+        ReputationEvent::Rated(rating) => {
+            reputation.rating_sum += Uint128::from(rating as u128 * SCALE);
+            reputation.rating_count += Uint128::from(SCALE);
+        }
+        ReputationEvent::Completed(volume) => {
+            reputation.completed += Uint128::from(SCALE);
+            match reputation.volume.iter_mut().find(|x| x.denom == volume.denom) {
+                Some(total) => total.amount = total.amount.saturating_add(volume.amount),
+                None => reputation.volume.push(volume)
+            }
+        }
+        ReputationEvent::DisputeWon => reputation.disputes_won += Uint128::from(SCALE),
+        ReputationEvent::DisputeLost => reputation.disputes_lost += Uint128::from(SCALE)
+    }
+    REPUTATION.save(storage, address, &reputation)
+}
+
+// The stored counters decayed to `now`
+pub fn load(storage: &dyn Storage, address: &Addr, now: Timestamp) -> StdResult<Reputation> {
+    let reputation = REPUTATION.may_load(storage, address)?.unwrap_or_default();
+    let elapsed = now.seconds().saturating_sub(reputation.updated.seconds());
+    Ok(Reputation {
+        rating_sum: decay(reputation.rating_sum, elapsed),
+        rating_count: decay(reputation.rating_count, elapsed),
+        completed: decay(reputation.completed, elapsed),
+        disputes_won: decay(reputation.disputes_won, elapsed),
+        disputes_lost: decay(reputation.disputes_lost, elapsed),
+        volume: reputation.volume.into_iter()
+            .map(|x| Coin { amount: decay(x.amount, elapsed), denom: x.denom })
+            .filter(|x| !x.amount.is_zero())
+            .collect(),
+        updated: now
+    })
+}
+
+// Addresses without history get a neutral 50 for rating and reliability. Experience is one
+// point per recent completed order and volume ten points per decimal digit of the largest
+// single-denom volume, both capped at 100.
+pub fn score(reputation: &Reputation) -> ReputationScore {
+    let rating = if reputation.rating_count.is_zero() {
+        50
+    } else {
+        (reputation.rating_sum.multiply_ratio(25u128, reputation.rating_count).u128() as u64).saturating_sub(25)
+    };
+    let good = reputation.completed + reputation.disputes_won;
+    let total = good + reputation.disputes_lost;
+    let reliability = if total.is_zero() { 50 } else { good.multiply_ratio(100u128, total).u128() as u64 };
+    let experience = (reputation.completed.u128() / SCALE).min(100) as u64;
+    let largest = reputation.volume.iter().map(|x| x.amount.u128()).max().unwrap_or_default();
+    let volume = (10 * decimal_digits(largest)).min(100);
+    ReputationScore {
+        score: (40 * rating + 30 * reliability + 20 * experience + 10 * volume) / 100,
+        rating,
+        reliability,
+        experience,
+        volume
+    }
+}
+
+// Halves the value per full HALF_LIFE and interpolates linearly within the last one
+fn decay(value: Uint128, elapsed: u64) -> Uint128 {
+    let halvings = elapsed / HALF_LIFE;
+    if halvings >= 128 {
+        return Uint128::zero();
+    }
+    let value = Uint128::from(value.u128() >> halvings);
+    value - value.multiply_ratio(elapsed % HALF_LIFE, 2 * HALF_LIFE)
+}
+
+fn decimal_digits(mut value: u128) -> u64 {
+    let mut digits = 0;
+    while value > 0 {
+        value /= 10;
+        digits += 1;
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::coin;
+    use cosmwasm_std::testing::MockStorage;
+
+    #[test]
+    fn test_decay() {
+        let value = Uint128::from(1000u128);
+        assert_eq!(value, decay(value, 0));
+        assert_eq!(Uint128::from(750u128), decay(value, HALF_LIFE / 2));
+        assert_eq!(Uint128::from(500u128), decay(value, HALF_LIFE));
+        assert_eq!(Uint128::from(375u128), decay(value, HALF_LIFE + HALF_LIFE / 2));
+        assert_eq!(Uint128::zero(), decay(Uint128::MAX, 128 * HALF_LIFE));
+        assert_eq!(Uint128::zero(), decay(value, u64::MAX));
+    }
+
+    #[test]
+    fn test_score() {
+        assert_eq!(ReputationScore {score: 35, rating: 50, reliability: 50, experience: 0, volume: 0}, score(&Reputation::default()));
+
+        let reputation = Reputation {
+            rating_sum: Uint128::from(10 * SCALE),
+            rating_count: Uint128::from(2 * SCALE),
+            completed: Uint128::from(3 * SCALE),
+            disputes_won: Uint128::zero(),
+            disputes_lost: Uint128::from(SCALE),
+            volume: vec![coin(12345, "LUNA"), coin(99, "earth")],
+            updated: Timestamp::from_seconds(0)
+        };
+        assert_eq!(ReputationScore {score: 68, rating: 100, reliability: 75, experience: 3, volume: 50}, score(&reputation));
+    }
+
+    #[test]
+    fn test_volume_per_denom() {
+        let mut storage = MockStorage::new();
+        let address = Addr::unchecked("seller");
+        let now = Timestamp::from_seconds(0);
+        for volume in [coin(600, "LUNA"), coin(600, "earth"), coin(300, "LUNA")] {
+            record(&mut storage, &address, now, ReputationEvent::Completed(volume)).unwrap();
+        }
+        let reputation = load(&storage, &address, now).unwrap();
+        assert_eq!(vec![coin(900, "LUNA"), coin(600, "earth")], reputation.volume);
+        // 900 has three digits, adding up the denoms would have given four
+        assert_eq!(30, score(&reputation).volume);
+
+        let reputation = load(&storage, &address, now.plus_seconds(128 * HALF_LIFE)).unwrap();
+        assert!(reputation.volume.is_empty());
+    }
+}
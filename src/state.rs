@@ -1,14 +1,57 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Coin};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Binary, Coin, Timestamp, Uint128};
+use cw_storage_plus::{Item, Map, U32Key};
 
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
     pub order_cnt: u32,
+    #[serde(default)]
+    pub offer_cnt: u32,
     pub owner: Addr,
+    #[serde(default)]
+    pub min_shipper_stake: Option<Coin>,
+    #[serde(default = "default_collateral_bps")]
+    pub collateral_bps: u64,  // collateral a bidding shipper posts, in basis points of the order price
+    #[serde(default = "default_insurance_bps")]
+    pub insurance_bps: u64,  // share of slashed collateral paid into the insurance pool
+    #[serde(default = "default_arbiter")]
+    pub arbiter: Addr,  // may read the encrypted addresses of every order, nobody until the owner sets one on older state
+    #[serde(default = "default_max_batch_size")]
+    pub max_batch_size: u32,  // most operations a single batch message may carry
+}
+
+// Fields added after the first release default to these when older state is loaded
+pub fn default_collateral_bps() -> u64 {
+    10_000
+}
+
+pub fn default_insurance_bps() -> u64 {
+    1_000
+}
+
+pub fn default_max_batch_size() -> u32 {
+    50
+}
+
+fn default_arbiter() -> Addr {
+    Addr::unchecked("")
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Shipper {
+    pub address: Addr,
+    pub service_areas: Vec<String>,
+    pub pub_key: String,  // default key for bids that do not carry their own
+    #[serde(default)]
+    pub key_algorithm: EncryptionAlgorithm,
+    pub capacity: u32,
+    pub active_orders: u32,
+    pub stake: Coin,
+    pub rate_per_km: Option<Coin>,
+    pub max_radius_km: Option<u32>
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -17,7 +60,88 @@ pub struct Goods {
     pub seller: Addr,
     pub price: Coin,
     pub seller_area: String,
-    pub status: GoodsStatus
+    #[serde(default)]
+    pub location: Option<Location>,
+    #[serde(default)]
+    pub listing: Listing,
+    pub status: GoodsStatus,
+    #[serde(default)]
+    pub store: Option<Addr>,
+    #[serde(default)]
+    pub metadata: GoodsMetadata,
+    #[serde(default = "default_stock")]
+    pub stock: u32,  // units left, the goods are ordered once the last one is
+    #[serde(default)]
+    pub reserved: u32  // units held by orders that are not settled yet
+}
+
+fn default_stock() -> u32 {
+    1
+}
+
+// Searchable listing details, the description itself is kept off chain and referenced by its sha256
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct GoodsMetadata {
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    pub description_hash: Option<Binary>,
+    pub image_uris: Vec<String>
+}
+
+// Storefront of a seller. Listings posted while the store exists link to it and fall back to
+// its default area when posted without a seller area.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Store {
+    pub owner: Addr,
+    pub name: String,
+    pub description_hash: Binary,
+    pub default_area: String,
+    pub return_policy: ReturnPolicy,
+    pub payout_address: Addr,  // credited with the proceeds of the store's sales
+    pub accepted_denoms: Vec<String>,  // denoms listings may be priced in, empty accepts any
+    // Shipping the store pays for its buyers. When an order is delivered, up to this amount of the
+    // shipping fee is taken from the proceeds and refunded to the buyer. Only applies when the
+    // price and the fee are in the same denom as the cap.
+    pub shipping_subsidy: Option<Coin>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReturnPolicy {
+    NoReturns,
+    ReturnWithin {days: u32}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub enum Listing {
+    #[default]
+    FixedPrice,
+    EnglishAuction(EnglishAuction),
+    DutchAuction(DutchAuction)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EnglishAuction {
+    pub reserve_price: Uint128,
+    pub min_increment: Uint128,
+    pub end_time: Timestamp,
+    pub highest_bid: Option<AuctionBid>
+}
+
+// the price falls linearly from start_price at start_time to floor_price at end_time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DutchAuction {
+    pub start_price: Uint128,
+    pub floor_price: Uint128,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AuctionBid {
+    pub bidder: Addr,
+    pub amount: Coin,
+    pub buyer_area: String
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -28,26 +152,239 @@ pub struct Order {
     pub goods: Goods,
     pub price: Coin,
     pub buyer_area: String,
+    #[serde(default)]
+    pub buyer_location: Option<Location>,
     pub shipper_bids: Vec<ShipperBid>,
     pub shipping_fee: Coin,
     pub shipper: Addr,
     pub shipper_key: String,
-    pub buyer_addr_enc: Vec<u8>,
+    #[serde(default)]
+    pub shipper_key_algorithm: EncryptionAlgorithm,
+    pub buyer_addr_enc: Vec<u8>,  // raw RSA PKCS#1 v1.5 ciphertext of legacy uploads
     pub seller_addr_enc: Vec<u8>,
+    #[serde(default)]
+    pub buyer_address: Option<AddressEnvelope>,
+    #[serde(default)]
+    pub seller_address: Option<AddressEnvelope>,
+    #[serde(default)]
+    pub bidding: BiddingMode,
+    #[serde(default)]
+    pub auto_select: Option<AutoSelect>,
+    #[serde(default)]
+    pub route: Vec<ShippingLeg>,  // empty on orders stored before relay routes
+    #[serde(default)]
+    pub custody_leg: u32,
+    #[serde(default)]
+    pub faulty_leg: Option<u32>,
+    #[serde(default)]
+    pub contest_deadline: Option<Timestamp>,  // the blamed leg may contest a broken-goods claim until then
+    #[serde(default)]
+    pub contested: bool,  // contested claims are settled by the arbiter
+    #[serde(default)]
+    pub delivery_lock: Option<Binary>,  // sha256 of the secret the buyer hands the courier
+    #[serde(default)]
+    pub proof_deadline: Option<Timestamp>,  // disputes wait for this when the order is locked
+    #[serde(default)]
+    pub reassignment: Option<ReassignmentProposal>,
+    #[serde(default)]
+    pub completed: Option<Timestamp>,  // when the order was confirmed or the dispute settled
+    #[serde(default)]
+    pub items: Vec<OrderItem>,  // cart orders only, `goods` is then the first item
+    #[serde(default)]
+    pub disputed_items: Vec<OrderItem>,  // empty when the whole order is disputed
     pub status: OrderStatus
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OrderItem {
+    pub name: String,
+    pub quantity: u32,
+    pub unit_price: Coin
+}
+
+// One shipper's part of the route. The shipper of the leg in custody is mirrored in order.shipper,
+// order.shipping_fee is the sum of all leg fees.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ShippingLeg {
+    pub shipper: Addr,
+    pub pub_key: String,
+    #[serde(default)]
+    pub algorithm: EncryptionAlgorithm,
+    pub fee: Coin,
+    pub deposit: Coin,
+    pub handed_off: bool,  // confirmed by this leg's shipper when passing the package on
+    pub received: bool  // confirmed by this leg's shipper when taking the package over
+}
+
+// The buyer escrows 2 * max_fee up front so the contract can pick a shipper on its own
+// once bidding_end has passed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AutoSelect {
+    pub bidding_end: Timestamp,
+    pub policy: SelectionPolicy,
+    pub max_fee: Coin
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum SelectionPolicy {
+    LowestPrice,
+    BestReputation,
+    Weighted { price_weight: u64, reputation_weight: u64 }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub enum BiddingMode {
+    #[default]
+    Open,
+    Sealed(SealedBidding)
+}
+
+// Shippers commit during the commit period and reveal during the reveal period,
+// only revealed bids end up in order.shipper_bids.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SealedBidding {
+    pub commit_end: Timestamp,
+    pub reveal_end: Timestamp,
+    pub deposit: Coin,
+    pub commitments: Vec<BidCommitment>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BidCommitment {
+    pub shipper: Addr,
+    pub commitment: Binary,  // sha256(shipper|price|salt|pub_key|algorithm)
+    pub revealed: bool
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Offer {
+    pub id: u32,
+    pub goods_name: String,
+    pub buyer: Addr,
+    pub price: Coin,  // escrowed with the offer
+    pub counter_price: Option<Coin>,
+    pub buyer_area: String,
+    pub expires: Timestamp,
+    pub status: OfferStatus
+}
+
+// Replacement for the shipper in custody. It takes effect once both buyer and seller approved
+// and the new shipper posted its collateral.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReassignmentProposal {
+    pub shipper: Addr,
+    pub approvals: Vec<Addr>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Reassignment {
+    pub leg: u32,
+    pub from: Addr,
+    pub to: Addr,
+    pub time: Timestamp
+}
+
+// Public key a user registered to receive encrypted messages. Registering a new key
+// revokes the previous one, revoked keys stay in the key history.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserKey {
+    pub pub_key: String,
+    pub algorithm: EncryptionAlgorithm,
+    pub registered: Timestamp,
+    pub revoked: Option<Timestamp>
+}
+
+// One message of an order's log, encrypted once per recipient
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Message {
+    pub sender: Addr,
+    pub time: Timestamp,
+    pub entries: Vec<MessageEntry>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MessageEntry {
+    pub recipient: Addr,
+    pub envelope: AddressEnvelope
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Buyer,
+    Seller,
+    Shipper
+}
+
+// Rating from 1 to 5 one participant of a completed order gave another, the review text
+// itself is kept off chain and referenced by its sha256.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Review {
+    pub order_id: u32,
+    pub reviewer: Addr,
+    pub reviewer_role: Role,
+    pub subject: Addr,
+    pub subject_role: Role,
+    pub rating: u8,
+    pub review_hash: Binary,
+    pub time: Timestamp
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Checkpoint {
+    pub kind: CheckpointKind,
+    pub shipper: Addr,
+    pub time: Timestamp,
+    pub note_enc: Option<Binary>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum CheckpointKind {
+    PickedUp,
+    InTransit { area: String },
+    OutForDelivery,
+    DeliveryAttempted
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ShipperBid {
     pub shipper: Addr,
     pub pub_key: String,
-    pub price: Coin
+    #[serde(default)]
+    pub algorithm: EncryptionAlgorithm,
+    pub price: Coin,
+    #[serde(default)]
+    pub deposit: Coin  // empty on bids stored before deposits were recorded
+}
+
+// Scheme of a shipper key. Keys are PEM encoded SubjectPublicKeyInfo, orders stored before
+// the tag existed are RSA PKCS#1 v1.5.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EncryptionAlgorithm {
+    #[default]
+    RsaPkcs1v15,
+    RsaOaep,
+    X25519Ecies
+}
+
+// An address encrypted for the shipper key. Version 0 wraps a legacy raw upload,
+// the nonce is required by X25519/ECIES and unused by the RSA schemes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AddressEnvelope {
+    pub algorithm: EncryptionAlgorithm,
+    pub version: u32,
+    pub nonce: Option<Binary>,
+    pub ciphertext: Binary
+}
+
+// Coordinates in microdegrees, e.g. 45_501_689 for 45.501689
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+pub struct Location {
+    pub latitude: i32,
+    pub longitude: i32
 }
 
-//pub struct Location {
-////    pub latitude: i32,
-////    pub longitude: i32
-////}
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub enum GoodsStatus {
     Available,
@@ -56,6 +393,12 @@ pub enum GoodsStatus {
     Returned
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum OfferStatus {
+    Open,
+    Countered
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub enum OrderStatus {
     Setup,
@@ -71,3 +414,33 @@ pub enum OrderStatus {
 pub const STATE: Item<State> = Item::new("state");
 pub const GOODS_LIST: Map<&str, Goods> = Map::new("goods_list");
 pub const ORDER_LIST: Map<&str, Order> = Map::new("order_list");
+// shipment timeline of each order, keyed by order id
+pub const TRACKING: Map<&str, Vec<Checkpoint>> = Map::new("tracking");
+// open offers keyed by (goods name, offer id), closed offers are removed
+pub const OFFER_LIST: Map<(&str, U32Key), Offer> = Map::new("offer_list");
+// listing categories managed by the owner
+pub const CATEGORIES: Map<&str, bool> = Map::new("categories");
+// secondary indexes of GOODS_LIST keyed by (category, goods name) and (tag, goods name)
+pub const GOODS_BY_CATEGORY: Map<(&str, &str), bool> = Map::new("goods_by_category");
+pub const GOODS_BY_TAG: Map<(&str, &str), bool> = Map::new("goods_by_tag");
+pub const STORES: Map<&Addr, Store> = Map::new("stores");
+// secondary index of GOODS_LIST keyed by (store owner, goods name)
+pub const STORE_GOODS: Map<(&Addr, &str), bool> = Map::new("store_goods");
+pub const SHIPPERS: Map<&Addr, Shipper> = Map::new("shippers");
+// secondary index of SHIPPERS keyed by (service area, shipper)
+pub const SHIPPERS_BY_AREA: Map<(&str, &Addr), bool> = Map::new("shippers_by_area");
+// insurance pool per denom, funded by slashed collateral
+pub const INSURANCE_POOL: Map<&str, Uint128> = Map::new("insurance_pool");
+// reviews by (subject, order id, reviewer)
+pub const REVIEWS: Map<(&Addr, &str, &Addr), Review> = Map::new("reviews");
+// encrypted message log per order
+pub const MESSAGES: Map<&str, Vec<Message>> = Map::new("messages");
+// current key of every user, and all keys a user ever registered, oldest first
+pub const USER_KEYS: Map<&Addr, UserKey> = Map::new("user_keys");
+pub const KEY_HISTORY: Map<&Addr, Vec<UserKey>> = Map::new("key_history");
+// completed shipper reassignments per order
+pub const REASSIGNMENTS: Map<&str, Vec<Reassignment>> = Map::new("reassignments");
+// public key each address signs its query permits with
+pub const PERMIT_KEYS: Map<&Addr, Binary> = Map::new("permit_keys");
+// claimable balances credited by settlements, keyed by (recipient, denom)
+pub const CLAIMABLE: Map<(&Addr, &str), Uint128> = Map::new("claimable");